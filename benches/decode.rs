@@ -4,7 +4,7 @@ fn decode_buffer(c: &mut Criterion) {
     let mut buf = (0..32_768).map(|c| (c % 256) as u8).collect::<Vec<u8>>();
     let length = buf.len();
     let mut encoded = Vec::with_capacity(32_768 * 102 / 100);
-    yenc::encode_buffer(&buf, 0, 128, &mut encoded).unwrap();
+    yenc::encode_buffer(&buf, yenc::EncodeState::start(), 128, &mut encoded).unwrap();
 
     let mut group = c.benchmark_group("decode");
 
@@ -37,7 +37,7 @@ fn decode_stream(c: &mut Criterion) {
             b.iter(|| {
                 let i = input.clone();
                 let mut input_r = std::io::Cursor::new(i);
-                let options = yenc::DecodeOptions::new("/tmp");
+                let options = yenc::DecodeOptions::new("/tmp").overwrite(true);
                 options.decode_stream(&mut input_r).unwrap();
             });
         });
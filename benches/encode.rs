@@ -11,7 +11,7 @@ fn encode_buffer(c: &mut Criterion) {
         .bench_function("encode 32k", move |b| {
             b.iter(|| {
                 output.clear();
-                yenc::encode_buffer(&buf, 0, 128, &mut output).unwrap()
+                yenc::encode_buffer(&buf, yenc::EncodeState::start(), 128, &mut output).unwrap()
             })
         });
 }
@@ -35,5 +35,29 @@ fn encode_stream(c: &mut Criterion) {
         });
 }
 
-criterion_group!(benches, encode_buffer, encode_stream);
+fn encode_stream_read_buffer_size(c: &mut Criterion) {
+    let mut group = c.benchmark_group("encode_stream");
+    group
+        .throughput(Throughput::Bytes(1_048_576))
+        .bench_function("encode_stream 1M, 64k buffer", |b| {
+            b.iter(|| {
+                let buf = (0..1_048_576).map(|c| (c % 256) as u8).collect::<Vec<u8>>();
+                let length = buf.len();
+                let options = yenc::EncodeOptions::new().read_buffer_size(65_536);
+                let output = vec![0; length * 110 / 100];
+                let mut input_r = Cursor::new(buf);
+                let mut output_r = Cursor::new(output);
+                options
+                    .encode_stream(&mut input_r, &mut output_r, length as u64, "test")
+                    .unwrap();
+            })
+        });
+}
+
+criterion_group!(
+    benches,
+    encode_buffer,
+    encode_stream,
+    encode_stream_read_buffer_size
+);
 criterion_main!(benches);
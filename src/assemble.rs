@@ -0,0 +1,209 @@
+use super::decode::Header;
+
+use std::collections::BTreeMap;
+
+/// The maximum number of part numbers returned by `MultipartAssembler::missing_parts`.
+const MAX_MISSING_PARTS: usize = 1000;
+
+/// Keeps track of the parts of a multipart file that have been decoded.
+///
+/// Parts can be added in any order. The part number and total number of parts are taken from
+/// the `=ybegin` line, the begin and end offsets from the `=ypart` line.
+///
+/// # Example
+/// ```rust
+/// let mut assembler = yenc::MultipartAssembler::new();
+/// let header = yenc::parse_header(b"=ybegin part=2 total=3 line=128 size=300 name=a.bin\r\n").unwrap();
+/// assembler.add_part(&header);
+/// assert_eq!(vec![1, 3], assembler.missing_parts());
+/// assert!(!assembler.is_complete());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct MultipartAssembler {
+    total: Option<u32>,
+    size: Option<usize>,
+    // part number -> (begin, end) offsets, when present
+    parts: BTreeMap<u32, Option<(usize, usize)>>,
+}
+
+impl MultipartAssembler {
+    /// Constructs a new MultipartAssembler, without any parts.
+    pub fn new() -> MultipartAssembler {
+        Default::default()
+    }
+
+    /// Registers the part described by the header of a decoded segment.
+    ///
+    /// Headers without a part number are ignored. Adding the same part twice has no effect.
+    pub fn add_part(&mut self, meta: &Header) {
+        let part = match meta.part() {
+            Some(part) => part,
+            None => return,
+        };
+        if meta.total().is_some() {
+            self.total = meta.total();
+        }
+        if meta.size().is_some() {
+            self.size = meta.size();
+        }
+        let range = match (meta.begin(), meta.end()) {
+            (Some(begin), Some(end)) => Some((begin, end)),
+            _ => None,
+        };
+        self.parts.insert(part, range);
+    }
+
+    /// Returns the part numbers that have not been added yet, in ascending order.
+    ///
+    /// When the total number of parts is unknown, only the gaps below the highest added part
+    /// number are returned. The `total` comes from an untrusted header, so at most the first
+    /// 1000 missing part numbers are returned.
+    pub fn missing_parts(&self) -> Vec<u32> {
+        (1..=self.last_part())
+            .filter(|part| !self.parts.contains_key(part))
+            .take(MAX_MISSING_PARTS)
+            .collect()
+    }
+
+    /// Returns whether all parts have been added and, when the offsets and the file size are
+    /// known, whether the parts cover the complete file without gaps.
+    ///
+    /// Parts without offsets are left out of the coverage check, which is skipped when no part
+    /// has offsets.
+    pub fn is_complete(&self) -> bool {
+        if self.total.is_none() || self.missing_count() > 0 {
+            return false;
+        }
+        let mut ranges = self.parts.values().flatten().copied().collect::<Vec<_>>();
+        if ranges.is_empty() {
+            return true;
+        }
+        ranges.sort_unstable();
+        let mut covered = 0;
+        for (begin, end) in ranges {
+            if begin > covered + 1 {
+                return false;
+            }
+            covered = covered.max(end);
+        }
+        match self.size {
+            Some(size) => covered >= size,
+            None => true,
+        }
+    }
+
+    /// The highest part number: the total number of parts, when known, or else the highest
+    /// added part number.
+    fn last_part(&self) -> u32 {
+        match self.total {
+            Some(total) => total,
+            None => self.parts.keys().next_back().copied().unwrap_or(0),
+        }
+    }
+
+    /// The number of parts up to `last_part` that have not been added yet, without allocating.
+    fn missing_count(&self) -> u64 {
+        match self.last_part() {
+            0 => 0,
+            last => u64::from(last) - self.parts.range(1..=last).count() as u64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MultipartAssembler;
+    use crate::decode::parse_header;
+
+    fn header(part: u32, begin: usize, end: usize) -> crate::decode::Header {
+        let mut header = parse_header(
+            format!(
+                "=ybegin part={} total=3 line=128 size=300 name=a.bin\r\n",
+                part
+            )
+            .as_bytes(),
+        )
+        .unwrap();
+        header.merge_part(
+            parse_header(format!("=ypart begin={} end={}\r\n", begin, end).as_bytes()).unwrap(),
+        );
+        header
+    }
+
+    #[test]
+    fn assemble_out_of_order() {
+        let mut assembler = MultipartAssembler::new();
+        assert!(assembler.missing_parts().is_empty());
+        assert!(!assembler.is_complete());
+
+        assembler.add_part(&header(3, 201, 300));
+        assert_eq!(vec![1, 2], assembler.missing_parts());
+        assembler.add_part(&header(1, 1, 100));
+        assert_eq!(vec![2], assembler.missing_parts());
+        assert!(!assembler.is_complete());
+        assembler.add_part(&header(2, 101, 200));
+        assert!(assembler.missing_parts().is_empty());
+        assert!(assembler.is_complete());
+    }
+
+    #[test]
+    fn assemble_gap_in_range() {
+        let mut assembler = MultipartAssembler::new();
+        assembler.add_part(&header(1, 1, 100));
+        assembler.add_part(&header(2, 102, 200));
+        assembler.add_part(&header(3, 201, 300));
+        assert!(assembler.missing_parts().is_empty());
+        assert!(!assembler.is_complete());
+    }
+
+    #[test]
+    fn assemble_huge_total() {
+        let mut assembler = MultipartAssembler::new();
+        assembler.add_part(
+            &parse_header(b"=ybegin part=2 total=4294967295 line=128 size=300 name=a.bin\r\n")
+                .unwrap(),
+        );
+        let missing = assembler.missing_parts();
+        assert_eq!(1000, missing.len());
+        assert_eq!((1, 1001), (missing[0], missing[999]));
+        assert!(!assembler.is_complete());
+
+        let mut assembler = MultipartAssembler::new();
+        assembler.add_part(
+            &parse_header(b"=ybegin part=1 total=0 line=128 size=300 name=a.bin\r\n").unwrap(),
+        );
+        assert!(assembler.missing_parts().is_empty());
+        // does not panic on the empty range of part numbers
+        let _ = assembler.is_complete();
+    }
+
+    #[test]
+    fn assemble_checks_ranges_of_other_parts() {
+        let mut assembler = MultipartAssembler::new();
+        assembler.add_part(&header(1, 1, 100));
+        assembler.add_part(
+            &parse_header(b"=ybegin part=2 total=3 line=128 size=300 name=a.bin\r\n").unwrap(),
+        );
+        assembler.add_part(&header(3, 250, 300));
+        assert!(assembler.missing_parts().is_empty());
+        assert!(!assembler.is_complete());
+
+        let mut assembler = MultipartAssembler::new();
+        for part in 1..=3 {
+            let line = format!(
+                "=ybegin part={} total=3 line=128 size=300 name=a.bin\r\n",
+                part
+            );
+            assembler.add_part(&parse_header(line.as_bytes()).unwrap());
+        }
+        assert!(assembler.is_complete());
+    }
+
+    #[test]
+    fn assemble_ignores_single_part() {
+        let mut assembler = MultipartAssembler::new();
+        assembler.add_part(&parse_header(b"=ybegin line=128 size=300 name=a.bin\r\n").unwrap());
+        assert!(assembler.missing_parts().is_empty());
+        assert!(!assembler.is_complete());
+    }
+}
@@ -0,0 +1,117 @@
+//! Decodes yEnc encoded files to an output directory.
+//!
+//! Usage: `ydecode [OPTIONS] <INPUT>...`
+//!
+//! With `--join`, the inputs are the parts of one multipart file, which are decoded into a
+//! single output file. Run `ydecode --help` for the options.
+
+use clap::Parser;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// Decodes yEnc encoded files to an output directory.
+#[derive(Debug, Parser)]
+#[command(version)]
+struct Args {
+    /// The files to decode
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+
+    /// The directory of the decoded files
+    #[arg(long, default_value = ".")]
+    out_dir: PathBuf,
+
+    /// Decode the inputs as the parts of one multipart file, in any order, into one file,
+    /// and verify the checksum of the complete file
+    #[arg(long)]
+    join: bool,
+
+    /// Overwrite existing output files
+    #[arg(long)]
+    overwrite: bool,
+}
+
+fn main() {
+    let args = Args::parse();
+    let decode_options = yenc::DecodeOptions::new(args.out_dir.as_path()).overwrite(args.overwrite);
+    let result = if args.join {
+        join(&decode_options, &args)
+    } else {
+        decode(&decode_options, &args.inputs)
+    };
+    if let Err(message) = result {
+        eprintln!("{}", message);
+        process::exit(1);
+    }
+}
+
+/// Formats the error message for the input file.
+fn error_message(input: &Path, err: impl std::fmt::Display) -> String {
+    format!("{}: {}", input.display(), err)
+}
+
+/// Decodes every input to its own output file.
+fn decode(decode_options: &yenc::DecodeOptions<&Path>, inputs: &[PathBuf]) -> Result<(), String> {
+    for input in inputs {
+        let result = decode_options
+            .decode_file(input)
+            .map_err(|err| error_message(input, err))?;
+        println!("{}", result.path.display());
+    }
+    Ok(())
+}
+
+/// Decodes all inputs, which are the parts of one file, into that file.
+///
+/// The headers are read first, to check that all parts are present. The parts are then
+/// decoded at their begin offset into the same file, and the checksum of the complete file
+/// is verified against the `crc32` of the `=yend` line of the last part, when present.
+fn join(decode_options: &yenc::DecodeOptions<&Path>, args: &Args) -> Result<(), String> {
+    let mut assembler = yenc::MultipartAssembler::new();
+    let mut named_header = None;
+    for input in &args.inputs {
+        let header = File::open(input)
+            .map_err(yenc::DecodeError::from)
+            .and_then(|file| decode_options.decode_header_only(file))
+            .map_err(|err| error_message(input, err))?;
+        if header.part().is_none() {
+            return Err(error_message(input, "not a part of a multipart file"));
+        }
+        assembler.add_part(&header);
+        if named_header.is_none() && header.name().is_some() {
+            named_header = Some(header);
+        }
+    }
+    let header = named_header.ok_or_else(|| error_message(&args.inputs[0], "missing filename"))?;
+    let name = header.name().unwrap_or_default().to_string();
+    if !assembler.is_complete() {
+        let message = format!("incomplete, missing parts {:?}", assembler.missing_parts());
+        return Err(error_message(Path::new(&name), message));
+    }
+
+    // the sanitized path, that the parts are decoded to
+    let output_path = decode_options
+        .output_path(&header)
+        .map_err(|err| error_message(Path::new(&name), err))?;
+    if !args.overwrite && output_path.exists() {
+        return Err(error_message(&output_path, "file exists"));
+    }
+    let decode_options = decode_options.clone().override_filename(name);
+    let mut crc32 = None;
+    let mut output_path = output_path;
+    for input in &args.inputs {
+        let result = decode_options
+            .decode_file(input)
+            .map_err(|err| error_message(input, err))?;
+        crc32 = crc32.or(result.crc32);
+        output_path = result.path;
+    }
+    if let Some(crc32) = crc32 {
+        decode_options
+            .verify_crc(&output_path, crc32)
+            .map_err(|err| error_message(&output_path, err))?;
+    }
+    println!("{}", output_path.display());
+    Ok(())
+}
@@ -0,0 +1,112 @@
+//! Encodes a file to one or more yEnc encoded files.
+//!
+//! Usage: `yencode [OPTIONS] <INPUT>`
+//!
+//! Run `yencode --help` for the options.
+
+use clap::Parser;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// Encodes a file to one or more yEnc encoded files.
+#[derive(Debug, Parser)]
+#[command(version)]
+struct Args {
+    /// The file to encode
+    input: PathBuf,
+
+    /// The number of parts
+    #[arg(long, conflicts_with = "part_size")]
+    parts: Option<u32>,
+
+    /// The maximum number of (unencoded) bytes per part
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    part_size: Option<u64>,
+
+    /// The maximum number of characters per line
+    #[arg(long, default_value_t = 128, value_parser = clap::value_parser!(u16).range(1..))]
+    line_length: u16,
+
+    /// The directory of the output files [default: the directory of the input file]
+    #[arg(long)]
+    output_dir: Option<PathBuf>,
+
+    /// The name of the output files, `{name}` is replaced with the name of the input file,
+    /// and `{part}` with the part number [default: `{name}.yenc` for a single part,
+    /// `{name}.{part}.yenc` for multiple parts]
+    #[arg(long)]
+    output: Option<String>,
+}
+
+fn main() {
+    let args = Args::parse();
+    let encode_options = yenc::EncodeOptions::new().line_length(args.line_length);
+    if let Err(err) = encode_options.check_options() {
+        eprintln!("Invalid encode options: {}", err);
+        process::exit(2);
+    }
+    if let Err(err) = encode(&args, encode_options) {
+        eprintln!("{}: {}", args.input.display(), err);
+        process::exit(1);
+    }
+}
+
+fn encode(args: &Args, encode_options: yenc::EncodeOptions) -> Result<(), yenc::EncodeError> {
+    let length = args.input.metadata()?.len();
+    let parts = match (args.parts, args.part_size) {
+        (Some(parts), _) => parts,
+        (None, Some(part_size)) => parts_for_size(length, part_size)?,
+        (None, None) => 1,
+    };
+
+    let mut encode_options = encode_options;
+    if parts > 1 {
+        encode_options = encode_options.file_crc32(yenc::crc32_file(&args.input)?);
+    }
+    let part_infos = encode_options.encode_file_multipart(&args.input, parts, |part| {
+        File::create(output_path(args, part, parts))
+    })?;
+    for part_info in part_infos {
+        println!("{}", output_path(args, part_info.part, parts).display());
+    }
+    Ok(())
+}
+
+/// Returns the smallest number of parts for which `plan_parts` makes no part larger than
+/// `part_size` bytes.
+///
+/// `plan_parts` adds the remainder to the last part, so the length divided by `part_size`
+/// can be too few parts; the count is raised until the last part fits.
+fn parts_for_size(length: u64, part_size: u64) -> Result<u32, yenc::EncodeError> {
+    let parts = (length / part_size + u64::from(length % part_size != 0)).max(1);
+    let mut parts = u32::try_from(parts).map_err(|_| yenc::EncodeError::InvalidPartCount)?;
+    while length / u64::from(parts) + length % u64::from(parts) > part_size {
+        parts = parts
+            .checked_add(1)
+            .ok_or(yenc::EncodeError::InvalidPartCount)?;
+    }
+    Ok(parts)
+}
+
+/// Returns the path of the output file of the part.
+fn output_path(args: &Args, part: u32, parts: u32) -> PathBuf {
+    let name = args
+        .input
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    let template = match (&args.output, parts) {
+        (Some(template), _) => template.as_str(),
+        (None, 1) => "{name}.yenc",
+        (None, _) => "{name}.{part}.yenc",
+    };
+    let filename = template
+        .replace("{name}", &name)
+        .replace("{part}", &format!("{:03}", part));
+    let output_dir = match &args.output_dir {
+        Some(output_dir) => output_dir.as_path(),
+        None => args.input.parent().unwrap_or_else(|| Path::new("")),
+    };
+    output_dir.join(filename)
+}
@@ -0,0 +1,391 @@
+//! Encoding and decoding of byte slices, without allocating.
+//!
+//! These functions have no dependency on `std`, and are available without the `std` feature.
+
+use super::constants::{CR, DOT, ESCAPE, ESCAPE_OFFSET, LF, NUL, OFFSET, SPACE, TAB};
+use super::errors::{DecodeError, EncodeError};
+
+/// The state of the encoder between calls of `encode_buffer`: the column in the current line.
+///
+/// Start with `EncodeState::start()`, and pass the returned state to the next call, so the
+/// lines are wrapped correctly when the input is split across several buffers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EncodeState {
+    col: u16,
+}
+
+impl EncodeState {
+    /// The state at the start of a line.
+    pub const fn start() -> EncodeState {
+        EncodeState { col: 0 }
+    }
+
+    /// The number of characters already written in the current line.
+    pub fn col(self) -> u16 {
+        self.col
+    }
+
+    /// The state after `col` characters in the current line.
+    pub(crate) fn at_col(col: u16) -> EncodeState {
+        EncodeState { col }
+    }
+}
+
+/// Returns the maximum number of encoded bytes for `input_len` input bytes and the specified
+/// line length, including the line endings.
+///
+/// Every byte can be escaped, so at most `2 * input_len` characters are written. A line ends
+/// after at least `line_length` characters, so there are at most
+/// `2 * input_len / line_length + 1` line endings of at most two bytes (CRLF):
+///
+/// `max_encoded_len = 2 * input_len + 2 * (2 * input_len / line_length + 1)`
+///
+/// A line length of 0 is treated as 1. An output buffer of this size is always large enough
+/// for `encode_buffer_into`, for any state.
+///
+/// # Example
+/// ```rust
+/// let input = [0xd6u8; 100];
+/// let mut output = [0u8; yenc::max_encoded_len(100, 128)];
+/// let state = yenc::EncodeState::start();
+/// assert!(yenc::encode_buffer_into(&input, state, 128, &mut output).is_ok());
+/// ```
+pub const fn max_encoded_len(input_len: usize, line_length: u16) -> usize {
+    let line_length = if line_length == 0 {
+        1
+    } else {
+        line_length as usize
+    };
+    let max_chars = 2 * input_len;
+    max_chars + 2 * (max_chars / line_length + 1)
+}
+
+/// Encodes the input buffer into the output slice, without allocating.
+///
+/// Lines are wrapped with a maximum of `line_length` characters per line, and end with CRLF.
+/// A SPACE or TAB at the start or the end of a line is escaped.
+/// Returns the number of encoded bytes written to `output` and the new state.
+/// Pass `EncodeState::start()` for the first buffer.
+///
+/// # Errors
+/// - `EncodeError::BufferTooSmall` when the encoded data does not fit in `output`
+///
+/// # Example
+/// ```rust
+/// let mut output = [0u8; 8];
+/// let state = yenc::EncodeState::start();
+/// let (length, state) = yenc::encode_buffer_into(b"yEnc", state, 128, &mut output).unwrap();
+/// assert_eq!(b"\xa3o\x98\x8d", &output[..length]);
+/// assert_eq!(4, state.col());
+/// ```
+pub fn encode_buffer_into(
+    input: &[u8],
+    state: EncodeState,
+    line_length: u16,
+    output: &mut [u8],
+) -> Result<(usize, EncodeState), EncodeError> {
+    let mut length = 0;
+    let push = |byte: u8| {
+        if let Some(output_byte) = output.get_mut(length) {
+            *output_byte = byte;
+        }
+        length += 1;
+    };
+    let col = encode_with(
+        input,
+        state.col,
+        line_length,
+        &[CR, LF],
+        |_| false,
+        false,
+        push,
+    );
+    if length > output.len() {
+        return Err(EncodeError::BufferTooSmall { needed: length });
+    }
+    Ok((length, EncodeState::at_col(col)))
+}
+
+/// Encodes the input byte by byte, and passes every output byte to `push`.
+///
+/// The encoder loop of all encoders: critical characters and the encoded bytes for which
+/// `escape` returns true are escaped, a dot at the start of a line is doubled, and a SPACE or
+/// TAB at the edge of a line is escaped. With `end_of_data`, the input ends the data, so a
+/// SPACE or TAB in the last byte is escaped, as it ends the last line. Lines end with `eol`.
+/// Returns the new column offset.
+#[inline(always)]
+pub(crate) fn encode_with<E, P>(
+    input: &[u8],
+    col: u16,
+    line_length: u16,
+    eol: &[u8],
+    escape: E,
+    end_of_data: bool,
+    mut push: P,
+) -> u16
+where
+    E: Fn(u8) -> bool,
+    P: FnMut(u8),
+{
+    let mut col = col;
+    for (i, &b) in input.iter().enumerate() {
+        let mut encoded = ENCODE_TABLE[b as usize];
+        let last_line_end = end_of_data && i + 1 == input.len();
+        if escape(encoded.0)
+            || is_whitespace_at_line_edge(encoded.0, col, line_length)
+            || (last_line_end && matches!(encoded.0, SPACE | TAB))
+        {
+            encoded = (ESCAPE, encoded.0.wrapping_add(ESCAPE_OFFSET));
+        }
+        push(encoded.0);
+        // saturating, so the column does not overflow at a line length of `u16::MAX`
+        col = col.saturating_add(match encoded.0 {
+            ESCAPE => {
+                push(encoded.1);
+                2
+            }
+            DOT if col == 0 => {
+                push(DOT);
+                2
+            }
+            _ => 1,
+        });
+        if col >= line_length {
+            eol.iter().for_each(|&byte| push(byte));
+            col = 0;
+        }
+    }
+    col
+}
+
+/// Returns whether the encoded byte is a SPACE or TAB at the start of a line, or at the end of
+/// a line of `line_length` characters, where it may be stripped in transport.
+#[inline(always)]
+pub(crate) fn is_whitespace_at_line_edge(encoded_byte: u8, col: u16, line_length: u16) -> bool {
+    matches!(encoded_byte, SPACE | TAB) && (col == 0 || col + 1 >= line_length)
+}
+
+/// The result of `encode_byte` for every byte value, so the encoder can index instead of branch.
+pub(crate) static ENCODE_TABLE: [(u8, u8); 256] = encode_table();
+
+const fn encode_table() -> [(u8, u8); 256] {
+    let mut table = [(0, 0); 256];
+    let mut i = 0;
+    while i < table.len() {
+        table[i] = encode_byte(i as u8);
+        i += 1;
+    }
+    table
+}
+
+/// Encodes a single byte.
+///
+/// Returns the encoded byte, or `ESCAPE` and the escaped byte when the byte is a critical character.
+#[inline(always)]
+pub(crate) const fn encode_byte(input_byte: u8) -> (u8, u8) {
+    let mut output = (0, 0);
+
+    let output_byte = input_byte.overflowing_add(OFFSET).0;
+    match output_byte {
+        LF | CR | NUL | ESCAPE => {
+            output.0 = ESCAPE;
+            output.1 = output_byte.overflowing_add(ESCAPE_OFFSET).0;
+        }
+        _ => {
+            output.0 = output_byte;
+        }
+    };
+    output
+}
+
+/// Decode the encoded byte slice into the output slice, without allocating.
+///
+/// Returns the number of decoded bytes written to `output`.
+/// The decoded data is never larger than the input, so an output slice of
+/// `input.len()` bytes is always large enough.
+///
+/// An escape character at the end of `input` is ignored, so a chunk must not end between an
+/// escape character and the escaped byte.
+///
+/// # Errors
+/// - `DecodeError::BufferTooSmall` when the decoded data does not fit in `output`
+/// - `DecodeError::DanglingEscape` when an escape character is followed by CR or LF
+pub fn decode_buffer_into(input: &[u8], output: &mut [u8]) -> Result<usize, DecodeError> {
+    let mut length = 0;
+    let mut iter = input.iter().cloned().peekable();
+    let mut line_start = true;
+    while let Some(byte) = iter.next() {
+        let mut result_byte = byte;
+        match byte {
+            NUL | CR => {
+                // for now, just continue
+                continue;
+            }
+            LF => {
+                line_start = true;
+                continue;
+            }
+            DOT if line_start && iter.peek() == Some(&DOT) => {
+                iter.next();
+            }
+            ESCAPE => {
+                match iter.next() {
+                    Some(CR | LF) => return Err(DecodeError::DanglingEscape),
+                    Some(b) => {
+                        result_byte = b.overflowing_sub(ESCAPE_OFFSET).0;
+                    }
+                    None => {
+                        // for now, just continue
+                        continue;
+                    }
+                }
+            }
+            _ => {}
+        }
+        line_start = false;
+        if let Some(output_byte) = output.get_mut(length) {
+            *output_byte = result_byte.overflowing_sub(OFFSET).0;
+        }
+        length += 1;
+    }
+    if length > output.len() {
+        return Err(DecodeError::BufferTooSmall { needed: length });
+    }
+    Ok(length)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::constants::{CR, ESCAPE, LF, NUL, SPACE};
+    use super::{
+        decode_buffer_into, encode_buffer_into, encode_byte, max_encoded_len, EncodeState,
+        ENCODE_TABLE,
+    };
+    use crate::errors::{DecodeError, EncodeError};
+
+    #[test]
+    fn escape_null() {
+        assert_eq!((ESCAPE, 0x40), encode_byte(214));
+    }
+
+    #[test]
+    fn escape_lf() {
+        assert_eq!((ESCAPE, 0x4A), encode_byte(214 + LF));
+    }
+
+    #[test]
+    fn escape_cr() {
+        assert_eq!((ESCAPE, 0x4D), encode_byte(214 + CR));
+    }
+
+    #[test]
+    fn escape_equal_sign() {
+        assert_eq!((ESCAPE, 0x7D), encode_byte(ESCAPE - 42));
+    }
+
+    #[test]
+    fn non_escaped() {
+        for x in 0..256u16 {
+            let encoded = (x as u8).overflowing_add(42).0;
+            if encoded != NUL && encoded != CR && encoded != LF && encoded != ESCAPE {
+                assert_eq!((encoded, 0), encode_byte(x as u8));
+            }
+        }
+    }
+
+    #[test]
+    fn encode_table() {
+        for (x, &encoded) in ENCODE_TABLE.iter().enumerate() {
+            assert_eq!(encode_byte(x as u8), encoded);
+        }
+    }
+
+    #[test]
+    fn encode_into_slice() {
+        let mut output = [0u8; 10];
+        assert_eq!(
+            (8, EncodeState::start()),
+            encode_buffer_into(b"yEnc", EncodeState::start(), 2, &mut output).unwrap()
+        );
+        assert_eq!(b"\xa3o\r\n\x98\x8d\r\n", &output[..8]);
+    }
+
+    #[test]
+    fn encode_into_slice_space_at_line_edges() {
+        const S: u8 = SPACE.wrapping_sub(42);
+        const A: u8 = b'a' - 42;
+        let mut output = [0u8; 16];
+        let (length, _) =
+            encode_buffer_into(&[S, A, S, A, S], EncodeState::start(), 4, &mut output).unwrap();
+        assert_eq!(b"=`a=`\r\na ", &output[..length]);
+        let (length, _) =
+            encode_buffer_into(&[A, S, A, S], EncodeState::start(), 128, &mut output).unwrap();
+        assert_eq!(b"a a ", &output[..length]);
+    }
+
+    #[test]
+    fn encode_into_slice_max_encoded_len() {
+        let input = [0xd6u8; 300];
+        let mut output = [0u8; max_encoded_len(300, 7)];
+        let mut state = EncodeState::start();
+        for chunk in input.chunks(11) {
+            let (length, next_state) = encode_buffer_into(chunk, state, 7, &mut output).unwrap();
+            assert!(length <= max_encoded_len(chunk.len(), 7));
+            state = next_state;
+        }
+    }
+
+    #[test]
+    fn encode_into_slice_max_line_length() {
+        // 0xd6 is escaped, so the column would pass `u16::MAX` at the end of the first line
+        let input = vec![0xd6u8; 70_000];
+        let mut output = vec![0u8; max_encoded_len(input.len(), u16::MAX)];
+        let (length, state) =
+            encode_buffer_into(&input, EncodeState::start(), u16::MAX, &mut output).unwrap();
+        assert_eq!(140_004, length);
+        assert_eq!(8_928, state.col());
+        assert_eq!(&output[65_536..65_538], &[CR, LF]);
+    }
+
+    #[test]
+    fn encode_into_slice_too_small() {
+        let mut output = [0u8; 2];
+        assert_eq!(
+            Err(EncodeError::BufferTooSmall { needed: 4 }),
+            encode_buffer_into(&[0x00, 0x01, 0xd6], EncodeState::start(), 128, &mut output)
+        );
+    }
+
+    #[test]
+    fn decode_dangling_escape() {
+        let mut output = [0u8; 8];
+        assert_eq!(
+            Err(DecodeError::DanglingEscape),
+            decode_buffer_into(b"ab=\r\n", &mut output)
+        );
+        assert_eq!(
+            Err(DecodeError::DanglingEscape),
+            decode_buffer_into(b"ab=\n", &mut output)
+        );
+        assert_eq!(Ok(2), decode_buffer_into(b"ab=", &mut output));
+    }
+
+    #[test]
+    fn decode_into_slice() {
+        let mut output = [0u8; 4];
+        assert_eq!(
+            2,
+            decode_buffer_into(&[b'=', 0x01, 0xff], &mut output).unwrap()
+        );
+        assert_eq!([0xff - 0x40 - 0x2A + 2, 0xff - 0x2A], output[..2]);
+    }
+
+    #[test]
+    fn decode_into_slice_too_small() {
+        let mut output = [0u8; 1];
+        assert_eq!(
+            Err(DecodeError::BufferTooSmall { needed: 3 }),
+            decode_buffer_into(&[0x01, 0x02, 0x03], &mut output)
+        );
+    }
+}
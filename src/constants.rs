@@ -1,8 +1,22 @@
+//! The special byte values of the yEnc encoding, and the defaults of this crate.
+
+/// NUL, a critical character that is always escaped.
 pub const NUL: u8 = 0;
-//pub const TAB: u8 = b'\t';
+/// Horizontal tab, escaped at the start and end of a line.
+pub const TAB: u8 = b'\t';
+/// Line Feed, a critical character that is always escaped.
 pub const LF: u8 = b'\n';
+/// Carriage Return, a critical character that is always escaped.
 pub const CR: u8 = b'\r';
+/// Space, escaped at the start and end of a line.
 pub const SPACE: u8 = b' ';
+/// The escape character: an escaped byte is written as `=` followed by the byte + 64.
 pub const ESCAPE: u8 = b'=';
+/// A dot at the start of a line is doubled, for NNTP.
 pub const DOT: u8 = b'.';
-pub const DEFAULT_LINE_SIZE: u8 = 128;
+/// The default line length of the encoder, in characters.
+pub const DEFAULT_LINE_LENGTH: u16 = 128;
+/// The offset that is added to every byte when encoding.
+pub const OFFSET: u8 = 42;
+/// The offset that is added to an escaped byte, after `OFFSET`.
+pub const ESCAPE_OFFSET: u8 = 64;
@@ -0,0 +1,153 @@
+#[cfg(feature = "std")]
+use std::{fs::File, io, io::Read, path::Path};
+
+/// Incremental CRC32 checksum, as used in the `crc32=` and `pcrc32=` fields.
+///
+/// Keeps track of the number of bytes, so the checksum and size of a
+/// (part of a) file can be computed in one pass.
+#[derive(Clone, Debug, Default)]
+pub struct Crc32 {
+    hasher: crc32fast::Hasher,
+    num_bytes: usize,
+}
+
+impl Crc32 {
+    /// Constructs a new Crc32, with no bytes processed.
+    pub fn new() -> Crc32 {
+        Default::default()
+    }
+
+    /// Updates the checksum with the bytes in the slice.
+    pub fn update_with_slice(&mut self, slice: &[u8]) {
+        self.hasher.update(slice);
+        self.num_bytes += slice.len();
+    }
+
+    /// Returns the checksum of all bytes processed so far.
+    pub fn crc(&self) -> u32 {
+        self.hasher.clone().finalize()
+    }
+
+    /// Returns the number of bytes processed so far.
+    pub fn num_bytes(&self) -> usize {
+        self.num_bytes
+    }
+
+    /// Combines the checksum with the checksum of the data that follows it.
+    ///
+    /// After combining, `self` holds the checksum and number of bytes of the
+    /// concatenation of both data, without processing the data again.
+    pub fn combine(&mut self, other: &Crc32) {
+        self.hasher.combine(&other.hasher);
+        self.num_bytes += other.num_bytes;
+    }
+}
+
+/// Computes the CRC32 checksum of the data.
+///
+/// Gives the same result as the checksums written by the encoder.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut checksum = Crc32::new();
+    checksum.update_with_slice(data);
+    checksum.crc()
+}
+
+/// Computes the CRC32 checksum of the concatenation of two data, A followed by B,
+/// from the checksum of A, the checksum of B and the length of B.
+///
+/// Can be used to compute the `crc32` of a complete file from the `pcrc32` values of its parts.
+///
+/// # Example
+/// ```rust
+/// let crc_a = yenc::crc32(b"1234");
+/// let crc_b = yenc::crc32(b"56789");
+/// assert_eq!(yenc::crc32(b"123456789"), yenc::crc32_combine(crc_a, crc_b, 5));
+/// ```
+pub fn crc32_combine(crc_a: u32, crc_b: u32, len_b: usize) -> u32 {
+    let mut hasher = crc32fast::Hasher::new_with_initial_len(crc_a, 0);
+    hasher.combine(&crc32fast::Hasher::new_with_initial_len(
+        crc_b,
+        len_b as u64,
+    ));
+    hasher.finalize()
+}
+
+/// Computes the CRC32 checksum of a file, without encoding it.
+///
+/// The file is read in chunks of 8 KiB, like the encoder does, so the result can be
+/// compared with a published `crc32` value.
+///
+/// # Example
+/// ```rust,no_run
+/// let crc = yenc::crc32_file("test2.bin").unwrap();
+/// println!("crc32={:08x}", crc);
+/// ```
+/// # Errors
+/// - when the file cannot be opened or read
+#[cfg(feature = "std")]
+pub fn crc32_file<P: AsRef<Path>>(path: P) -> io::Result<u32> {
+    let mut input = File::open(path)?;
+    let mut checksum = Crc32::new();
+    let mut buffer = [0u8; 8192];
+    loop {
+        let length = input.read(&mut buffer)?;
+        if length == 0 {
+            break;
+        }
+        checksum.update_with_slice(&buffer[..length]);
+    }
+    Ok(checksum.crc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{crc32, crc32_combine, Crc32};
+
+    #[test]
+    fn crc32_empty() {
+        assert_eq!(0, crc32(&[]));
+    }
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(0xcbf4_3926, crc32(b"123456789"));
+    }
+
+    #[test]
+    fn crc32_incremental() {
+        let mut checksum = Crc32::new();
+        checksum.update_with_slice(b"1234");
+        checksum.update_with_slice(b"56789");
+        assert_eq!(0xcbf4_3926, checksum.crc());
+        assert_eq!(9, checksum.num_bytes());
+    }
+
+    #[test]
+    fn crc32_combine_parts() {
+        let data = (0..10_000).map(|c| (c % 251) as u8).collect::<Vec<u8>>();
+        let (a, b) = data.split_at(3_333);
+        assert_eq!(crc32(&data), crc32_combine(crc32(a), crc32(b), b.len()));
+        assert_eq!(crc32(a), crc32_combine(crc32(a), crc32(&[]), 0));
+    }
+
+    #[test]
+    fn crc32_combine_struct() {
+        let mut checksum_a = Crc32::new();
+        checksum_a.update_with_slice(b"1234");
+        let mut checksum_b = Crc32::new();
+        checksum_b.update_with_slice(b"56789");
+        checksum_a.combine(&checksum_b);
+        assert_eq!(0xcbf4_3926, checksum_a.crc());
+        assert_eq!(9, checksum_a.num_bytes());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn crc32_file_check_value() {
+        let mut path = std::env::temp_dir();
+        path.push("yenc_crc32_file_check_value.bin");
+        std::fs::write(&path, b"123456789").unwrap();
+        assert_eq!(0xcbf4_3926, super::crc32_file(&path).unwrap());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,23 +1,192 @@
-use std::fs::OpenOptions;
-use std::io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
-use std::path::Path;
+use std::ffi::OsString;
+use std::fs::{create_dir_all, remove_file, rename, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::num::{IntErrorKind, ParseIntError};
+use std::path::{Path, PathBuf};
 
-use super::constants::{CR, DEFAULT_LINE_SIZE, DOT, ESCAPE, LF, NUL, SPACE};
+#[cfg(feature = "async")]
+use std::{future::Future, pin::Pin};
+#[cfg(feature = "async")]
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, AsyncWrite,
+    AsyncWriteExt,
+};
+
+use super::buffer::decode_buffer_into;
+use super::constants::{
+    CR, DEFAULT_LINE_LENGTH, DOT, ESCAPE, ESCAPE_OFFSET, LF, NUL, OFFSET, SPACE,
+};
+use super::crc32::{crc32, crc32_file, Crc32};
+use super::encode::EncodeOptions;
 use super::errors::DecodeError;
 
 /// Options for decoding.
 /// The entry point for decoding from a file or (TCP) stream to an output directory.
-#[derive(Debug)]
+///
+/// A configured `DecodeOptions` can be cloned, for example to use it as a template for
+/// several decodes.
+#[derive(Clone, Debug)]
 pub struct DecodeOptions<P> {
     output_dir: P,
+    strict: bool,
+    checksum_policy: ChecksumPolicy,
+    allow_subdirs: bool,
+    overwrite: bool,
+    trim_filename: bool,
+    override_filename: Option<String>,
+    append: bool,
+    tolerant_prefix: bool,
+    ignore_unknown_keywords: bool,
+    nntp_dot_termination: bool,
+    max_output_bytes: Option<usize>,
+    on_error: OnError,
+    atomic: bool,
+    resume: Option<(u64, u64)>,
+}
+
+/// What to do when the CRC32 checksum of the decoded data does not match the
+/// checksum in the footer.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ChecksumPolicy {
+    /// Return `DecodeError::InvalidChecksum` (default).
+    #[default]
+    Fail,
+    /// Keep the decoded output, and return a result with `checksum_ok == false`.
+    Warn,
+}
+
+/// What to do with the output file when decoding fails.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OnError {
+    /// Leave the partially written output file, for recovery (default).
+    #[default]
+    Keep,
+    /// Remove the output file, when it was created by the failed decode.
+    Delete,
+}
+
+/// Called after each decoded line, with the decoded data, the running checksum and the metadata.
+type Observer<'a> = dyn FnMut(&[u8], &Crc32, &Header) + 'a;
+
+/// The output file of a yEnc block.
+#[derive(Debug)]
+struct OutputFile {
+    /// the final path of the output file
+    path: PathBuf,
+    /// the temporary file that is written, in atomic mode
+    temp_path: Option<PathBuf>,
+    /// whether the written file was created by the decode
+    created: bool,
+}
+
+impl OutputFile {
+    /// The path of the file that is written.
+    fn write_path(&self) -> &Path {
+        self.temp_path.as_deref().unwrap_or(&self.path)
+    }
+
+    /// Renames the temporary file to the final path, in atomic mode.
+    fn commit(&self) -> Result<(), DecodeError> {
+        if let Some(temp_path) = &self.temp_path {
+            rename(temp_path, &self.path)?;
+        }
+        Ok(())
+    }
+}
+
+/// Outcome of decoding the body of a yEnc block.
+#[derive(Debug)]
+struct Body {
+    footer_found: bool,
+    checksum: Crc32,
+}
+
+/// A line of the body of a yEnc block, after it was handled by `DecodeOptions::decode_line`.
+#[derive(Debug)]
+enum BodyLine {
+    /// A `=ypart` line, merged into the metadata.
+    Part,
+    /// The `=yend` line, merged into the metadata.
+    Footer,
+    /// A line that was skipped, like the NNTP dot line.
+    Skipped,
+    /// A data line, with the length of the decoded bytes.
+    Data(usize),
+}
+
+/// The future returned by the `on_part_begin` function of `DecodeOptions::decode_lines_async`.
+#[cfg(feature = "async")]
+type PartBeginFuture<'a> = Pin<Box<dyn Future<Output = Result<(), DecodeError>> + Send + 'a>>;
+
+/// Outcome of all checks on a decoded yEnc block, see `DecodeOptions::decode_stream_verbose`.
+///
+/// A check is `None` when it could not be performed, because the header field it
+/// depends on was absent.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationReport {
+    /// the number of decoded bytes written to the output file
+    pub bytes_written: usize,
+    /// whether the number of decoded bytes matches the part range or the size
+    pub size_ok: Option<bool>,
+    /// whether the checksum of the decoded bytes matches `pcrc32`
+    pub part_crc_ok: Option<bool>,
+    /// whether the checksum of the decoded bytes matches `crc32` (single part only)
+    pub file_crc_ok: Option<bool>,
+    /// whether the `=yend` line was found
+    pub footer_present: bool,
+}
+
+/// The result of a successful decode.
+///
+/// Contains the path of the output file, together with the metadata parsed from the
+/// `=ybegin`, `=ypart` and `=yend` lines. Fields are `None` when the corresponding
+/// keyword was not present in the article.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DecodeResult {
+    /// the path of the decoded output file, which is not necessarily valid UTF-8;
+    /// use `path.display()` to print it
+    pub path: PathBuf,
+    /// the filename, as specified in the header
+    pub name: Option<String>,
+    /// the part number (multipart only)
+    pub part: Option<u32>,
+    /// the total number of parts (multipart only)
+    pub total: Option<u32>,
+    /// the begin offset of the part (1-based, multipart only)
+    pub begin: Option<usize>,
+    /// the end offset of the part (multipart only)
+    pub end: Option<usize>,
+    /// the size, as specified in the footer, or in the header when the footer has none
+    pub size: Option<usize>,
+    /// the CRC32 checksum of the complete file
+    pub crc32: Option<u32>,
+    /// the CRC32 checksum of the part
+    pub pcrc32: Option<u32>,
+    /// `true` when a checksum was present and matched the decoded data
+    pub checksum_ok: bool,
+    /// the CRC32 checksum computed over the decoded data, to compare with
+    /// `pcrc32` or `crc32` when `checksum_ok` is `false`
+    pub computed_crc32: u32,
 }
 
-#[derive(Default, Debug)]
-struct MetaData {
+/// The metadata parsed from a `=ybegin`, `=ypart` or `=yend` line.
+///
+/// All fields are optional, they are `None` when the keyword is not present in the line.
+///
+/// With the `serde` feature, `Header` implements `Serialize` and `Deserialize`. The checksums
+/// are (de)serialized as lowercase hexadecimal strings, as they appear in the header line.
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Header {
     name: Option<String>,
+    // not serialized, the output filename falls back to `name`
+    #[cfg_attr(feature = "serde", serde(skip))]
+    name_bytes: Option<Vec<u8>>,
     line_length: Option<u16>,
     size: Option<usize>,
+    #[cfg_attr(feature = "serde", serde(with = "hex_crc32"))]
     crc32: Option<u32>,
+    #[cfg_attr(feature = "serde", serde(with = "hex_crc32"))]
     pcrc32: Option<u32>,
     part: Option<u32>,
     total: Option<u32>,
@@ -25,6 +194,132 @@ struct MetaData {
     end: Option<usize>,
 }
 
+impl Header {
+    /// The filename (`name=`).
+    ///
+    /// The name is the last keyword of the `=ybegin` line: everything after `name=` up to the
+    /// end of the line is taken verbatim, including spaces and `=` characters.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// The filename (`name=`) as raw bytes.
+    ///
+    /// Unlike `name`, non UTF-8 bytes (for example Latin-1 or Shift-JIS) are preserved.
+    pub fn name_bytes(&self) -> Option<&[u8]> {
+        self.name_bytes.as_deref()
+    }
+
+    /// The line length (`line=`).
+    pub fn line_length(&self) -> Option<u16> {
+        self.line_length
+    }
+
+    /// The size of the complete file (`size=`).
+    pub fn size(&self) -> Option<usize> {
+        self.size
+    }
+
+    /// The CRC32 checksum of the complete file (`crc32=`).
+    pub fn crc32(&self) -> Option<u32> {
+        self.crc32
+    }
+
+    /// The CRC32 checksum of the part (`pcrc32=`).
+    pub fn pcrc32(&self) -> Option<u32> {
+        self.pcrc32
+    }
+
+    /// The part number (`part=`).
+    pub fn part(&self) -> Option<u32> {
+        self.part
+    }
+
+    /// The total number of parts (`total=`).
+    pub fn total(&self) -> Option<u32> {
+        self.total
+    }
+
+    /// The begin offset of the part, 1-based (`begin=`).
+    pub fn begin(&self) -> Option<usize> {
+        self.begin
+    }
+
+    /// The end offset of the part (`end=`).
+    pub fn end(&self) -> Option<usize> {
+        self.end
+    }
+
+    /// Merges the fields of a `=ypart` line.
+    ///
+    /// Besides `begin` and `end`, some encoders put other fields, such as the part number and
+    /// total number of parts, in the `=ypart` line. Those are only used when the `=ybegin` line
+    /// has none.
+    pub(crate) fn merge_part(&mut self, part: Header) {
+        self.begin = part.begin;
+        self.end = part.end;
+        if self.name.is_none() {
+            self.name = part.name;
+            self.name_bytes = part.name_bytes;
+        }
+        self.line_length = self.line_length.or(part.line_length);
+        self.size = self.size.or(part.size);
+        self.part = self.part.or(part.part);
+        self.total = self.total.or(part.total);
+        self.crc32 = self.crc32.or(part.crc32);
+        self.pcrc32 = self.pcrc32.or(part.pcrc32);
+    }
+
+    /// Merges the fields of a `=yend` line.
+    ///
+    /// The part number and total number of parts of the `=ybegin` line are kept, those of the
+    /// `=yend` line are only used when the `=ybegin` line has none.
+    fn merge_footer(&mut self, footer: Header) {
+        if footer.size.is_some() {
+            self.size = footer.size;
+        }
+        self.part = self.part.or(footer.part);
+        self.total = self.total.or(footer.total);
+        self.crc32 = footer.crc32;
+        self.pcrc32 = footer.pcrc32;
+    }
+}
+
+/// (De)serializes an optional CRC32 checksum as a lowercase hexadecimal string.
+#[cfg(feature = "serde")]
+mod hex_crc32 {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(crc: &Option<u32>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match *crc {
+            Some(crc) => serializer.serialize_some(&format!("{:08x}", crc)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<u32>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(hex) => u32::from_str_radix(&hex, 16)
+                .map(Some)
+                .map_err(D::Error::custom),
+            None => Ok(None),
+        }
+    }
+}
+
+impl Default for DecodeOptions<PathBuf> {
+    /// Constructs new DecodeOptions with the current directory as output directory.
+    fn default() -> Self {
+        DecodeOptions::new(PathBuf::from("."))
+    }
+}
+
 impl<P> DecodeOptions<P>
 where
     P: AsRef<Path>,
@@ -32,11 +327,251 @@ where
     /// Construct new DecodeOptions using the specified path as output directory.
     /// The output directory is
     pub fn new(output_dir: P) -> DecodeOptions<P> {
-        DecodeOptions { output_dir }
+        DecodeOptions {
+            output_dir,
+            strict: false,
+            checksum_policy: ChecksumPolicy::Fail,
+            allow_subdirs: false,
+            overwrite: false,
+            trim_filename: true,
+            override_filename: None,
+            append: false,
+            tolerant_prefix: false,
+            ignore_unknown_keywords: false,
+            nntp_dot_termination: false,
+            max_output_bytes: None,
+            on_error: OnError::Keep,
+            atomic: false,
+            resume: None,
+        }
+    }
+
+    /// Sets strict mode (default=false).
+    /// In strict mode, non-whitespace data after the `=yend` line results in
+    /// `DecodeError::TrailingData`, a missing `=yend` line results in
+    /// `DecodeError::MissingFooter`, and a keyword that occurs twice in a header line results in
+    /// `DecodeError::DuplicateKeyword`.
+    pub fn strict(mut self, strict: bool) -> DecodeOptions<P> {
+        self.strict = strict;
+        self
+    }
+
+    /// Sets the policy for a checksum mismatch (default=`ChecksumPolicy::Fail`).
+    /// With `ChecksumPolicy::Warn` the output is kept and the mismatch is reported in the
+    /// result, which is useful for recovery tools.
+    pub fn checksum_policy(mut self, checksum_policy: ChecksumPolicy) -> DecodeOptions<P> {
+        self.checksum_policy = checksum_policy;
+        self
+    }
+
+    /// Allows the filename in the header to contain subdirectories (default=false).
+    ///
+    /// By default, only the last path component of the filename is used, so the output file is
+    /// always written directly in the output directory. When set, relative subdirectories are
+    /// kept and created when needed. Absolute paths and `..` components are always rejected
+    /// with `DecodeError::UnsafeFilename`. Only enable this for trusted input.
+    pub fn allow_subdirs(mut self, allow_subdirs: bool) -> DecodeOptions<P> {
+        self.allow_subdirs = allow_subdirs;
+        self
+    }
+
+    /// Allows writing to an existing output file (default=false).
+    ///
+    /// By default, decoding fails with an I/O error (`ErrorKind::AlreadyExists`) when the
+    /// output file already exists. When set, an existing file is opened without truncating it,
+    /// which is needed to decode the parts of a multipart file into the same output file.
+    pub fn overwrite(mut self, overwrite: bool) -> DecodeOptions<P> {
+        self.overwrite = overwrite;
+        self
+    }
+
+    /// Trims whitespace around the filename in the header (default=true).
+    ///
+    /// The `name=` value is taken verbatim up to the end of the line (carriage returns are
+    /// dropped). Before it is used as output filename:
+    /// 1. when set, leading and trailing whitespace is removed;
+    /// 2. when the name is wrapped in double quotes (`"..."`), one pair of quotes is removed.
+    ///
+    /// Disable this for posts where a leading or trailing space is part of the filename.
+    pub fn trim_filename(mut self, trim_filename: bool) -> DecodeOptions<P> {
+        self.trim_filename = trim_filename;
+        self
+    }
+
+    /// Uses the specified filename for the output file, instead of the filename in the header.
+    ///
+    /// This is useful for posts with obfuscated filenames. The filename is checked in the same
+    /// way as the filename in the header.
+    ///
+    /// To reassemble a multipart file, decode all parts with the same overridden filename: the
+    /// output file is then opened without truncating it, even when `overwrite` is not set, and
+    /// the decoded data of every part is written at its `begin` offset. The parts can be decoded
+    /// in any order.
+    pub fn override_filename<S>(mut self, filename: S) -> DecodeOptions<P>
+    where
+        S: Into<String>,
+    {
+        self.override_filename = Some(filename.into());
+        self
+    }
+
+    /// Appends the decoded data to the output file (default=false).
+    ///
+    /// When set, an existing output file is opened without truncating it, and the decoded data
+    /// is written at the end of the file, unless a `=ypart` line specifies the begin offset.
+    /// This concatenates segments that are posted as separate single part articles, when they
+    /// are decoded in order. Use `verify_crc` to check the complete file afterwards.
+    pub fn append(mut self, append: bool) -> DecodeOptions<P> {
+        self.append = append;
+        self
+    }
+
+    /// Tolerates a prefix before the `=ybegin` keyword (default=false).
+    ///
+    /// By default, the `=ybegin` line is only recognized when the line starts with `=ybegin `.
+    /// When set, a leading UTF-8 byte order mark, whitespace and quote markers (`>`) are
+    /// skipped first, so a yEnc block that is indented or quoted in an e-mail or forum post is
+    /// found. Only the `=ybegin` line itself is unquoted, not the lines that follow it.
+    pub fn tolerant_prefix(mut self, tolerant_prefix: bool) -> DecodeOptions<P> {
+        self.tolerant_prefix = tolerant_prefix;
+        self
+    }
+
+    /// Skips unknown keywords in the header lines (default=false).
+    ///
+    /// By default, a keyword that is not defined by the yEnc specification results in
+    /// `DecodeError::InvalidHeader`. When set, an unknown `keyword=value` pair is skipped, so
+    /// articles with tool specific fields (for example `desc=`, see
+    /// `EncodeOptions::extra_header_field`) can be decoded. The value of an unknown keyword
+    /// ends at the next space.
+    pub fn ignore_unknown_keywords(mut self, ignore_unknown_keywords: bool) -> DecodeOptions<P> {
+        self.ignore_unknown_keywords = ignore_unknown_keywords;
+        self
+    }
+
+    /// Treats a line with a single dot as a terminator (default=false).
+    ///
+    /// Some old posts contain the NNTP end-of-article marker, a line with only `.`, before the
+    /// `=yend` line. By default, that line is decoded as data, which adds a spurious byte.
+    /// When set, the line is skipped instead.
+    pub fn nntp_dot_termination(mut self, nntp_dot_termination: bool) -> DecodeOptions<P> {
+        self.nntp_dot_termination = nntp_dot_termination;
+        self
+    }
+
+    /// Sets the maximum number of decoded bytes per yEnc block (default=unlimited).
+    ///
+    /// Decoding stops with `DecodeError::OutputTooLarge` before the data that would exceed the
+    /// limit is written, regardless of the `size` in the header. This protects against posts
+    /// with a bogus size or an endless body. The output file is not preallocated to a `size`
+    /// above the limit. The partially written output file is left in place.
+    pub fn max_output_bytes(mut self, max_output_bytes: usize) -> DecodeOptions<P> {
+        self.max_output_bytes = Some(max_output_bytes);
+        self
+    }
+
+    /// Sets what to do with the output file when decoding fails (default=`OnError::Keep`).
+    ///
+    /// With `OnError::Delete`, the output file is removed when decoding the body fails, for
+    /// example with `DecodeError::InvalidChecksum`, `DecodeError::IncompleteData` or an I/O
+    /// error. A file that already existed before decoding (with `overwrite`, `append` or
+    /// `override_filename`) is never removed, because it can contain data of other parts.
+    /// Trailing data after the `=yend` line (strict mode) does not remove the file.
+    pub fn on_error(mut self, on_error: OnError) -> DecodeOptions<P> {
+        self.on_error = on_error;
+        self
+    }
+
+    /// Writes the output to a temporary file, and renames it to the final name only after all
+    /// checks passed (default=false).
+    ///
+    /// The temporary file is created in the same directory, with the name of the output file
+    /// followed by `.partNNN.tmp` (for a part) or `.tmp`. When decoding fails, the temporary
+    /// file is kept or removed according to `on_error`, and the final file is not touched.
+    /// Other processes never see a partially written file under the final name.
+    ///
+    /// Renaming is only atomic within a file system; the temporary file is always created next
+    /// to the output file for that reason. The final file is replaced, so atomic mode is not
+    /// suited to decode several parts into the same file. Ignored in `append` mode.
+    pub fn atomic(mut self, atomic: bool) -> DecodeOptions<P> {
+        self.atomic = atomic;
+        self
+    }
+
+    /// Resumes an interrupted decode of a block (default=not set).
+    ///
+    /// `stream_offset` is the offset in the stream of the first line that was not decoded yet,
+    /// and `output_offset` the number of bytes of the block (or part) that were written to the
+    /// output file before, as recorded by the caller, for example from the progress of
+    /// `decode_stream_with_progress`. The caller must resume on a line boundary: the stream
+    /// offset must be the start of a data line, otherwise an I/O error of kind `InvalidInput`
+    /// is returned.
+    ///
+    /// The stream is read from the start, so the header lines are parsed as usual, and the
+    /// lines before `stream_offset` are skipped without decoding them. The existing output
+    /// file is opened without truncating it, the checksum of the bytes written before is
+    /// computed from the file, and the output continues after them. Used by `decode_stream`,
+    /// `decode_bufread`, `decode_stream_with_progress`, `decode_stream_with` and
+    /// `decode_file`; ignored by the other decode functions, and in `atomic` mode.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// let decode_options = yenc::DecodeOptions::new("/tmp/decoded").resume_from(1_054_329, 1_048_576);
+    /// let input = std::fs::File::open("test2.bin.yenc").unwrap();
+    /// decode_options.decode_stream(input).unwrap();
+    /// ```
+    pub fn resume_from(mut self, stream_offset: u64, output_offset: u64) -> DecodeOptions<P> {
+        self.resume = Some((stream_offset, output_offset));
+        self
+    }
+
+    /// Returns the path of the output file for the block with the specified header.
+    ///
+    /// The path is the output directory joined with the filename override, or with the
+    /// filename of the header after it is trimmed and checked, as described in `trim_filename`
+    /// and `allow_subdirs`. This is the path that decoding the block writes to, so it can be
+    /// checked before decoding.
+    ///
+    /// # Example
+    /// ```rust
+    /// let decode_options = yenc::DecodeOptions::new("/tmp/decoded");
+    /// let header = yenc::parse_header(b"=ybegin line=128 size=4 name=sub/a.bin\r\n").unwrap();
+    /// let path = decode_options.output_path(&header).unwrap();
+    /// assert_eq!(std::path::Path::new("/tmp/decoded/a.bin"), path);
+    /// ```
+    /// # Errors
+    /// - `DecodeError::MissingFilename` when the header has no filename and no override is set
+    /// - `DecodeError::UnsafeFilename` when the filename is an absolute path or refers to a
+    ///   parent directory
+    pub fn output_path(&self, header: &Header) -> Result<PathBuf, DecodeError> {
+        let name = match (&self.override_filename, &header.name_bytes) {
+            (Some(filename), _) => Some(filename.as_bytes()),
+            (None, Some(name_bytes)) => Some(name_bytes.as_slice()),
+            (None, None) => header.name.as_deref().map(str::as_bytes),
+        };
+        let name = name.ok_or(DecodeError::MissingFilename)?;
+        Ok(self.output_dir.as_ref().join(self.output_filename(name)?))
+    }
+
+    /// Checks the CRC32 checksum of a (reassembled) file against the expected checksum, for
+    /// example the `crc32` of the complete file from the `=yend` line of the last part.
+    ///
+    /// # Errors
+    /// - `DecodeError::InvalidChecksum` when the checksum does not match
+    /// - when an I/O error occurs
+    pub fn verify_crc<Q>(&self, path: Q, expected_crc32: u32) -> Result<(), DecodeError>
+    where
+        Q: AsRef<Path>,
+    {
+        if crc32_file(path)? != expected_crc32 {
+            return Err(DecodeError::InvalidChecksum);
+        }
+        Ok(())
     }
+
     /// Decodes the input file in a new output file.
     ///
-    /// If ok, returns the path of the decoded file.
+    /// If ok, returns the path of the decoded file and the parsed metadata.
     ///
     /// # Example
     /// ```rust,no_run
@@ -44,161 +579,1783 @@ where
     /// decode_options.decode_file("test2.bin.yenc");
     /// ```
     /// # Errors
-    /// - when the output file already exists
+    /// - when the output file already exists, unless `overwrite` is set
     /// - when I/O error occurs
     ///
-    pub fn decode_file(&self, input_filename: &str) -> Result<Box<Path>, DecodeError> {
-        let mut input_file = OpenOptions::new().read(true).open(input_filename)?;
+    pub fn decode_file<Q>(&self, input_path: Q) -> Result<DecodeResult, DecodeError>
+    where
+        Q: AsRef<Path>,
+    {
+        let mut input_file = OpenOptions::new().read(true).open(input_path)?;
         self.decode_stream(&mut input_file)
     }
 
     /// Decodes the data from a stream to the specified directory.
     ///
     /// Writes the output to a file with the filename from the header line, and places it in the
-    /// output path. The path of the output file is returned as String.
-    pub fn decode_stream<R>(&self, read_stream: R) -> Result<Box<Path>, DecodeError>
+    /// output path. The path of the output file is returned together with the parsed metadata.
+    /// Data after the `=yend` line is ignored, unless strict mode is enabled.
+    /// Lines can end with CRLF, LF or CR.
+    ///
+    /// When the stream ends before the `=yend` line, the checksum cannot be verified, but the
+    /// number of decoded bytes is still checked against the part range (`begin` and `end`), or
+    /// against `size` for a single part article.
+    ///
+    /// After the `=ybegin` line, only lines that start with `=ypart ` or `=yend ` (with the
+    /// space) are control lines. Any other line is data, also when it starts with `=y`: the
+    /// escape sequence `=y` decodes to the byte `0x0f`. This encoder never writes a data line
+    /// that starts with `=y`, as `y` is not the escaped form of any critical character, but
+    /// data from other encoders that does so with `=ypart ` or `=yend ` cannot be told apart
+    /// from a control line.
+    ///
+    /// # Errors
+    /// - `DecodeError::InvalidChecksum` when the checksum does not match the decoded data
+    /// - `DecodeError::IncompleteData` when fewer or more bytes were decoded than expected
+    /// - `DecodeError::MissingFooter` when the `=yend` line is missing (strict mode only)
+    /// - `DecodeError::NoYencData` when the stream contains no `=ybegin` line
+    /// - `DecodeError::At` with the offset of the line in the stream, when a header or data
+    ///   line is invalid
+    /// - when an I/O error occurs
+    pub fn decode_stream<R>(&self, read_stream: R) -> Result<DecodeResult, DecodeError>
     where
         R: Read,
     {
-        let mut rdr = BufReader::new(read_stream);
-        let mut output_pathbuf = self.output_dir.as_ref().to_path_buf();
+        self.decode_stream_with_progress(read_stream, |_, _| {})
+    }
+
+    /// Decodes the data from a buffered stream to the specified directory, like
+    /// `decode_stream`.
+    ///
+    /// The stream is read directly, without an extra `BufReader`, so a reader that is already
+    /// buffered, for example a decompressor that implements `BufRead`, is not buffered twice.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// let decode_options = yenc::DecodeOptions::new("/tmp/decoded");
+    /// let input = std::io::BufReader::new(std::fs::File::open("test2.bin.yenc").unwrap());
+    /// decode_options.decode_bufread(input).unwrap();
+    /// ```
+    pub fn decode_bufread<R>(&self, read_stream: R) -> Result<DecodeResult, DecodeError>
+    where
+        R: BufRead,
+    {
+        self.decode_bufread_with_observer(read_stream, &mut |_, _, _| {})
+    }
 
-        let mut checksum = crc32fast::Hasher::new();
-        let mut yenc_block_found = false;
-        let mut metadata: MetaData = Default::default();
-        let mut num_bytes = 0;
+    /// Decodes the data from a stream to the directory `dir`, instead of the output directory of
+    /// the options, like `decode_stream`.
+    ///
+    /// All other options are kept, so one `DecodeOptions` can be used as a template for
+    /// decoding to several directories.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// let decode_options = yenc::DecodeOptions::new("/tmp/decoded").overwrite(true);
+    /// let input = std::fs::File::open("test2.bin.yenc").unwrap();
+    /// decode_options
+    ///     .decode_stream_to_dir(input, "/tmp/decoded/alt.binaries.test")
+    ///     .unwrap();
+    /// ```
+    pub fn decode_stream_to_dir<R, Q>(
+        &self,
+        read_stream: R,
+        dir: Q,
+    ) -> Result<DecodeResult, DecodeError>
+    where
+        R: Read,
+        Q: AsRef<Path>,
+    {
+        self.with_output_dir(dir).decode_stream(read_stream)
+    }
 
-        while !yenc_block_found {
-            let mut line_buf = Vec::<u8>::with_capacity(2 * DEFAULT_LINE_SIZE as usize);
-            let length = rdr.read_until(LF, &mut line_buf)?;
-            if length == 0 {
-                break;
-            }
-            if line_buf.starts_with(b"=ybegin ") {
-                yenc_block_found = true;
-                // parse header line and determine output filename
-                metadata = parse_header_line(&line_buf)?;
-                if let Some(ref name) = metadata.name {
-                    output_pathbuf.push(name.trim());
-                }
+    /// Returns a copy of the options, with `output_dir` as output directory.
+    fn with_output_dir<Q>(&self, output_dir: Q) -> DecodeOptions<Q> {
+        DecodeOptions {
+            output_dir,
+            strict: self.strict,
+            checksum_policy: self.checksum_policy,
+            allow_subdirs: self.allow_subdirs,
+            overwrite: self.overwrite,
+            trim_filename: self.trim_filename,
+            override_filename: self.override_filename.clone(),
+            append: self.append,
+            tolerant_prefix: self.tolerant_prefix,
+            ignore_unknown_keywords: self.ignore_unknown_keywords,
+            nntp_dot_termination: self.nntp_dot_termination,
+            max_output_bytes: self.max_output_bytes,
+            on_error: self.on_error,
+            atomic: self.atomic,
+            resume: self.resume,
+        }
+    }
+
+    /// Decodes the data from a stream to the specified directory, like `decode_stream`, and
+    /// reports the progress.
+    ///
+    /// After each decoded line, `progress` is called with the number of bytes decoded so far
+    /// and the expected number of bytes, when known from the part range or the size.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// let decode_options = yenc::DecodeOptions::new("/tmp/decoded");
+    /// let input = std::fs::File::open("test2.bin.yenc").unwrap();
+    /// decode_options
+    ///     .decode_stream_with_progress(input, |decoded, total| {
+    ///         if let Some(total) = total {
+    ///             println!("{}%", decoded * 100 / total.max(1));
+    ///         }
+    ///     })
+    ///     .unwrap();
+    /// ```
+    pub fn decode_stream_with_progress<R, F>(
+        &self,
+        read_stream: R,
+        mut progress: F,
+    ) -> Result<DecodeResult, DecodeError>
+    where
+        R: Read,
+        F: FnMut(usize, Option<usize>),
+    {
+        let mut observer = |_: &[u8], checksum: &Crc32, metadata: &Header| {
+            progress(checksum.num_bytes(), expected_size(metadata))
+        };
+        self.decode_bufread_with_observer(BufReader::new(read_stream), &mut observer)
+    }
+
+    /// Decodes the data from a stream to the specified directory, like `decode_stream`, and
+    /// reports every decoded chunk with the running checksum.
+    ///
+    /// After each decoded line, `on_chunk` is called with the decoded bytes of the line and the
+    /// CRC32 checksum of all bytes of the block (or part) decoded so far, so the verification
+    /// can be followed while decoding. After the last line, the running checksum is the
+    /// checksum that is compared with the `=yend` line.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// let decode_options = yenc::DecodeOptions::new("/tmp/decoded");
+    /// let input = std::fs::File::open("test2.bin.yenc").unwrap();
+    /// decode_options
+    ///     .decode_stream_with(input, |chunk, running_crc| {
+    ///         println!("{} bytes, crc32 so far {:08x}", chunk.len(), running_crc);
+    ///     })
+    ///     .unwrap();
+    /// ```
+    pub fn decode_stream_with<R, F>(
+        &self,
+        read_stream: R,
+        mut on_chunk: F,
+    ) -> Result<DecodeResult, DecodeError>
+    where
+        R: Read,
+        F: FnMut(&[u8], u32),
+    {
+        let mut observer =
+            |decoded: &[u8], checksum: &Crc32, _: &Header| on_chunk(decoded, checksum.crc());
+        self.decode_bufread_with_observer(BufReader::new(read_stream), &mut observer)
+    }
+
+    /// Decodes the data from a buffered stream, like `decode_stream`, and calls `observer`
+    /// after each decoded line.
+    fn decode_bufread_with_observer<R>(
+        &self,
+        mut rdr: R,
+        observer: &mut Observer<'_>,
+    ) -> Result<DecodeResult, DecodeError>
+    where
+        R: BufRead,
+    {
+        let resume = match self.atomic {
+            true => None,
+            false => self.resume,
+        };
+        match self.decode_block(&mut rdr, &mut 0, resume, observer)? {
+            Some(result) => {
+                self.check_trailing_data(&mut rdr)?;
+                Ok(result)
             }
+            None => Err(DecodeError::NoYencData),
+        }
+    }
+
+    /// Decodes all yEnc blocks from a stream to the specified directory.
+    ///
+    /// Some tools concatenate several `=ybegin ... =yend` blocks in one message. Every block is
+    /// written to its own output file, using the filename from its own header line. Lines
+    /// between blocks are skipped. A trailing block without `=yend` line is decoded up to the
+    /// end of the stream, or results in `DecodeError::MissingFooter` in strict mode.
+    ///
+    /// Returns the results of all decoded blocks, in the order in which they were found, or
+    /// `DecodeError::NoYencData` when the stream contains no `=ybegin` line.
+    pub fn decode_all<R>(&self, read_stream: R) -> Result<Vec<DecodeResult>, DecodeError>
+    where
+        R: Read,
+    {
+        let mut rdr = BufReader::new(read_stream);
+        let mut results = Vec::new();
+        let mut offset = 0;
+        while let Some(result) =
+            self.decode_block(&mut rdr, &mut offset, None, &mut |_, _, _| {})?
+        {
+            results.push(result);
+        }
+        if results.is_empty() {
+            return Err(DecodeError::NoYencData);
+        }
+        Ok(results)
+    }
+
+    /// Reads the `=ybegin` line with the header options of the decoder.
+    /// Returns `DecodeError::NoYencData` when the end of the stream is reached first.
+    fn read_first_header<R>(&self, rdr: &mut R, offset: &mut usize) -> Result<Header, DecodeError>
+    where
+        R: BufRead,
+    {
+        read_header(
+            rdr,
+            self.tolerant_prefix,
+            self.strict,
+            self.ignore_unknown_keywords,
+            offset,
+        )?
+        .ok_or(DecodeError::NoYencData)
+    }
+
+    /// Reads the `=ybegin` line from an asynchronous reader, like `read_first_header`.
+    #[cfg(feature = "async")]
+    async fn read_first_header_async<R>(
+        &self,
+        rdr: &mut R,
+        offset: &mut usize,
+    ) -> Result<Header, DecodeError>
+    where
+        R: AsyncBufRead + Unpin,
+    {
+        read_header_async(
+            rdr,
+            self.tolerant_prefix,
+            self.strict,
+            self.ignore_unknown_keywords,
+            offset,
+        )
+        .await?
+        .ok_or(DecodeError::NoYencData)
+    }
+
+    /// Decodes the next yEnc block from the reader to a file in the output directory.
+    /// With `resume`, the decode continues at the stream and output offset, see `resume_from`.
+    /// Returns `None` when no `=ybegin` line is found before the end of the stream.
+    fn decode_block<R>(
+        &self,
+        rdr: &mut R,
+        offset: &mut usize,
+        resume: Option<(u64, u64)>,
+        observer: &mut Observer<'_>,
+    ) -> Result<Option<DecodeResult>, DecodeError>
+    where
+        R: BufRead,
+    {
+        let mut metadata = match read_header(
+            rdr,
+            self.tolerant_prefix,
+            self.strict,
+            self.ignore_unknown_keywords,
+            offset,
+        )? {
+            Some(header) => header,
+            None => return Ok(None),
+        };
+
+        let (output_file, mut output) = self.open_output(&metadata, resume.is_some())?;
+        let result = match resume {
+            Some(resume) => self.skip_decoded(rdr, offset, &mut metadata, &mut output, resume),
+            None => Ok(Crc32::new()),
+        }
+        .and_then(|checksum| {
+            self.decode_lines(
+                rdr,
+                offset,
+                &mut metadata,
+                &mut output,
+                checksum,
+                seek_to_begin,
+                observer,
+            )
+        })
+        .and_then(|body| Ok((self.check_body(&metadata, &body)?, body)));
+        drop(output);
+        let (checksum_ok, body) = self.handle_error(result, &output_file)?;
+        output_file.commit()?;
+
+        Ok(Some(DecodeResult {
+            path: output_file.path,
+            name: metadata.name,
+            part: metadata.part,
+            total: metadata.total,
+            begin: metadata.begin,
+            end: metadata.end,
+            size: metadata.size,
+            crc32: metadata.crc32,
+            pcrc32: metadata.pcrc32,
+            checksum_ok,
+            computed_crc32: body.checksum.crc(),
+        }))
+    }
+
+    /// Decodes the data from a stream to the specified directory, like `decode_stream`, and
+    /// reports the outcome of every check instead of returning an error for the first
+    /// failed check.
+    ///
+    /// The output file is kept, even when some checks failed. Errors are only returned
+    /// for I/O errors, invalid header lines and a stream without `=ybegin` line
+    /// (`DecodeError::NoYencData`). On an error, the output file is kept or removed according
+    /// to `on_error`.
+    ///
+    /// In atomic mode, the temporary file is only renamed to the final name when no check
+    /// failed. Otherwise, the temporary file is kept or removed according to `on_error`, and
+    /// the final file is not touched.
+    pub fn decode_stream_verbose<R>(&self, read_stream: R) -> Result<ValidationReport, DecodeError>
+    where
+        R: Read,
+    {
+        let mut rdr = BufReader::new(read_stream);
+        let mut offset = 0;
+        let mut metadata = self.read_first_header(&mut rdr, &mut offset)?;
+
+        let (output_file, mut output) = self.open_output(&metadata, false)?;
+        let result = self.decode_lines(
+            &mut rdr,
+            &mut offset,
+            &mut metadata,
+            &mut output,
+            Crc32::new(),
+            seek_to_begin,
+            &mut |_, _, _| {},
+        );
+        drop(output);
+        let body = self.handle_error(result, &output_file)?;
+
+        let crc = body.checksum.crc();
+        let bytes_written = body.checksum.num_bytes();
+        let crc_ok = |expected: Option<u32>| match body.footer_found {
+            true => expected.map(|expected_crc| expected_crc == crc),
+            false => None,
+        };
+        let report = ValidationReport {
+            bytes_written,
+            size_ok: expected_size(&metadata).map(|size| size == bytes_written),
+            part_crc_ok: crc_ok(metadata.pcrc32),
+            file_crc_ok: match metadata.part {
+                None => crc_ok(metadata.crc32),
+                Some(_) => None,
+            },
+            footer_present: body.footer_found,
+        };
+        let check_failed =
+            [report.size_ok, report.part_crc_ok, report.file_crc_ok].contains(&Some(false));
+        if output_file.temp_path.is_none() || !check_failed {
+            output_file.commit()?;
+        } else if self.on_error == OnError::Delete {
+            remove_file(output_file.write_path())?;
+        }
+        Ok(report)
+    }
+
+    /// Removes the output file when decoding failed, depending on `on_error`.
+    fn handle_error<T>(
+        &self,
+        result: Result<T, DecodeError>,
+        output_file: &OutputFile,
+    ) -> Result<T, DecodeError> {
+        if result.is_err() && self.remove_on_error(output_file) {
+            remove_file(output_file.write_path())?;
         }
+        result
+    }
 
-        if yenc_block_found {
-            let output_file = OpenOptions::new()
-                .create(true)
-                .truncate(false)
-                .write(true)
-                .open(output_pathbuf.as_path())?;
+    /// Whether the output file is removed when decoding failed.
+    fn remove_on_error(&self, output_file: &OutputFile) -> bool {
+        output_file.created && self.on_error == OnError::Delete
+    }
 
-            if let Some(size) = metadata.size {
-                output_file.set_len(size as u64)?;
+    /// Creates (or opens) the output file for the block with the specified header.
+    /// In atomic mode, a temporary file is created instead. With `resume`, the existing file
+    /// is opened for reading and writing.
+    fn open_output(
+        &self,
+        metadata: &Header,
+        resume: bool,
+    ) -> Result<(OutputFile, BufWriter<File>), DecodeError> {
+        let (output_pathbuf, temp_path, open_options) = self.output_target(metadata, resume)?;
+        if let Some(parent) = output_pathbuf.parent() {
+            if self.allow_subdirs {
+                create_dir_all(parent)?;
             }
+        }
+        if temp_path.is_some() && !self.may_replace() && output_pathbuf.exists() {
+            return Err(io::Error::from(io::ErrorKind::AlreadyExists).into());
+        }
+
+        let write_path = temp_path.as_deref().unwrap_or(&output_pathbuf);
+        let created = temp_path.is_some() || !write_path.exists();
+        let mut output_file = open_options.open(write_path)?;
+        if self.append {
+            output_file.seek(SeekFrom::End(0))?;
+        } else if let Some(size) = self.preallocated_size(metadata) {
+            output_file.set_len(size)?;
+        }
+
+        let output = OutputFile {
+            path: output_pathbuf,
+            temp_path,
+            created,
+        };
+        Ok((output, BufWriter::new(output_file)))
+    }
+
+    /// Returns the final path, the temporary path (in atomic mode) and the open options of
+    /// the output file for the block with the specified header.
+    fn output_target(
+        &self,
+        metadata: &Header,
+        resume: bool,
+    ) -> Result<(PathBuf, Option<PathBuf>, OpenOptions), DecodeError> {
+        let output_pathbuf = self.output_path(metadata)?;
+
+        let mut open_options = OpenOptions::new();
+        if self.overwrite || self.append || self.override_filename.is_some() || resume {
+            open_options.create(true).truncate(false).read(resume);
+        } else {
+            open_options.create_new(true);
+        }
+        let temp_path = match self.atomic && !self.append {
+            true => {
+                let mut temp_name = output_pathbuf.file_name().unwrap_or_default().to_owned();
+                match metadata.part {
+                    Some(part) => temp_name.push(format!(".part{:03}.tmp", part)),
+                    None => temp_name.push(".tmp"),
+                }
+                open_options.create_new(false).create(true).truncate(true);
+                Some(output_pathbuf.with_file_name(temp_name))
+            }
+            false => None,
+        };
+        open_options.write(true);
+        Ok((output_pathbuf, temp_path, open_options))
+    }
+
+    /// Whether an existing file at the output path may be replaced.
+    fn may_replace(&self) -> bool {
+        self.overwrite || self.override_filename.is_some()
+    }
+
+    /// The length to preallocate the output file with, from the `size` of the header.
+    fn preallocated_size(&self, metadata: &Header) -> Option<u64> {
+        // a `size` above `max_output_bytes` is not trusted to preallocate the file
+        metadata
+            .size
+            .filter(|&size| self.max_output_bytes.map_or(true, |limit| size <= limit))
+            .map(|size| size as u64)
+    }
+
+    /// Decodes the data from a stream and writes the decoded bytes to the writer.
+    ///
+    /// Performs the same parsing and checksum validation as `decode_stream`, but does not
+    /// create a file in the output directory. The `begin` offset of a part is not used for
+    /// seeking, the decoded bytes of the part are written sequentially to `output`.
+    /// Returns the parsed metadata.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// let decode_options = yenc::DecodeOptions::new("/tmp/decoded");
+    /// let input = std::fs::File::open("test2.bin.yenc").unwrap();
+    /// let mut decoded = Vec::new();
+    /// let header = decode_options.decode_stream_to_writer(input, &mut decoded).unwrap();
+    /// ```
+    /// # Errors
+    /// - `DecodeError::NoYencData` when the stream contains no `=ybegin` line
+    /// - the errors of `decode_stream`, except for the errors of creating the output file
+    pub fn decode_stream_to_writer<R, W>(
+        &self,
+        read_stream: R,
+        output: W,
+    ) -> Result<Header, DecodeError>
+    where
+        R: Read,
+        W: Write,
+    {
+        let mut rdr = BufReader::new(read_stream);
+        let mut output = output;
+        let mut offset = 0;
+        let mut metadata = self.read_first_header(&mut rdr, &mut offset)?;
+        let body = self.decode_lines(
+            &mut rdr,
+            &mut offset,
+            &mut metadata,
+            &mut output,
+            Crc32::new(),
+            |_, _| Ok(()),
+            &mut |_, _, _| {},
+        )?;
+        self.check_body(&metadata, &body)?;
+        self.check_trailing_data(&mut rdr)?;
+        Ok(metadata)
+    }
+
+    /// Decodes the data from a stream into an open file, instead of a file in the output
+    /// directory.
+    ///
+    /// The decoded data of a part is written at the offset of its begin offset, other data
+    /// from the start of the file. The file is not truncated, so the caller can keep the
+    /// same handle open to write all parts of a multipart file, in any order. The caller is
+    /// responsible for opening the file with write access (and read access or create options
+    /// as needed); the file is not closed. Performs the same parsing and checksum validation
+    /// as `decode_stream`. Returns the parsed metadata.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// let decode_options = yenc::DecodeOptions::new("/tmp/decoded");
+    /// let mut file = std::fs::OpenOptions::new()
+    ///     .write(true)
+    ///     .create(true)
+    ///     .open("/tmp/decoded/test2.bin")
+    ///     .unwrap();
+    /// for part in ["test2.bin.yenc.001", "test2.bin.yenc.002"] {
+    ///     let input = std::fs::File::open(part).unwrap();
+    ///     decode_options.decode_stream_into_file(input, &mut file).unwrap();
+    /// }
+    /// ```
+    /// # Errors
+    /// - `DecodeError::NoYencData` when the stream contains no `=ybegin` line
+    /// - the errors of `decode_stream`, except for the errors of creating the output file
+    pub fn decode_stream_into_file<R>(
+        &self,
+        read_stream: R,
+        file: &mut File,
+    ) -> Result<Header, DecodeError>
+    where
+        R: Read,
+    {
+        let mut rdr = BufReader::new(read_stream);
+        let mut offset = 0;
+        let mut metadata = self.read_first_header(&mut rdr, &mut offset)?;
+        file.seek(SeekFrom::Start(0))?;
+        let mut output = BufWriter::new(file);
+        let body = self.decode_lines(
+            &mut rdr,
+            &mut offset,
+            &mut metadata,
+            &mut output,
+            Crc32::new(),
+            seek_to_begin,
+            &mut |_, _, _| {},
+        )?;
+        output.flush()?;
+        self.check_body(&metadata, &body)?;
+        self.check_trailing_data(&mut rdr)?;
+        Ok(metadata)
+    }
+
+    /// Reads and parses the `=ybegin` line, and the `=ypart` line when present, without
+    /// decoding the body.
+    ///
+    /// Much cheaper than a full decode, when only the name, part number or size is needed.
+    /// The fields of the `=yend` line are not available.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// let decode_options = yenc::DecodeOptions::new("/tmp/decoded");
+    /// let input = std::fs::File::open("test2.bin.yenc").unwrap();
+    /// let header = decode_options.decode_header_only(input).unwrap();
+    /// println!("{:?} part {:?} of {:?}", header.name(), header.part(), header.total());
+    /// ```
+    /// # Errors
+    /// - `DecodeError::NoYencData` when the stream contains no `=ybegin` line
+    /// - `DecodeError::InvalidHeader` when the `=ybegin` or `=ypart` line is invalid
+    /// - `DecodeError::IoError` when reading the stream fails
+    pub fn decode_header_only<R>(&self, read_stream: R) -> Result<Header, DecodeError>
+    where
+        R: Read,
+    {
+        let mut rdr = BufReader::new(read_stream);
+        let mut offset = 0;
+        let mut metadata = self.read_first_header(&mut rdr, &mut offset)?;
+        let mut line_buf = Vec::<u8>::with_capacity(2 * DEFAULT_LINE_LENGTH as usize);
+        read_line(&mut rdr, &mut line_buf)?;
+        if line_buf.starts_with(b"=ypart ") {
+            metadata.merge_part(
+                parse_header_line_with(&line_buf, self.strict, self.ignore_unknown_keywords)
+                    .map_err(at(offset))?,
+            );
+        }
+        Ok(metadata)
+    }
+
+    /// Decodes the lines following the `=ybegin` line up to and including the `=yend` line.
+    ///
+    /// The `=ypart` and `=yend` fields are merged into `metadata`, all other lines, including
+    /// lines that start with `=y` followed by another keyword, are decoded. The decoded bytes are
+    /// added to `checksum`, which holds the bytes decoded before, when resuming. When a `=ypart` line
+    /// with a begin offset is read, `on_part_begin` is called with that offset.
+    /// After each decoded line, `observer` is called with the decoded data.
+    /// `offset` is the offset in the stream, and is advanced with every line read.
+    #[allow(clippy::too_many_arguments)]
+    fn decode_lines<R, W, F>(
+        &self,
+        rdr: &mut R,
+        offset: &mut usize,
+        metadata: &mut Header,
+        output: &mut W,
+        mut checksum: Crc32,
+        mut on_part_begin: F,
+        observer: &mut Observer<'_>,
+    ) -> Result<Body, DecodeError>
+    where
+        R: BufRead,
+        W: Write,
+        F: FnMut(&mut W, usize) -> Result<(), DecodeError>,
+    {
+        let mut line_buf = Vec::<u8>::with_capacity(2 * DEFAULT_LINE_LENGTH as usize);
+        let mut decoded = Vec::<u8>::with_capacity(2 * DEFAULT_LINE_LENGTH as usize);
+
+        let mut footer_found = false;
+        while !footer_found {
+            line_buf.clear();
+            let length = read_line(rdr, &mut line_buf)?;
+            if length == 0 {
+                break;
+            }
+            let line_offset = *offset;
+            *offset += length;
+            match self.decode_line(
+                &line_buf,
+                line_offset,
+                metadata,
+                &mut checksum,
+                &mut decoded,
+            )? {
+                BodyLine::Part => {
+                    if let Some(begin) = metadata.begin {
+                        on_part_begin(output, begin)?;
+                    }
+                }
+                BodyLine::Footer => footer_found = true,
+                BodyLine::Skipped => {}
+                BodyLine::Data(decoded_length) => {
+                    let decoded = &decoded[..decoded_length];
+                    output.write_all(decoded)?;
+                    observer(decoded, &checksum, metadata);
+                }
+            }
+        }
+        output.flush()?;
+
+        Ok(Body {
+            footer_found,
+            checksum,
+        })
+    }
+
+    /// Handles one line of the body of a block, for `decode_lines` and `decode_lines_async`.
+    ///
+    /// The fields of a `=ypart` or `=yend` line are merged into `metadata`. A data line is
+    /// decoded into `decoded` and added to `checksum`. `line_offset` is the offset of the line
+    /// in the stream, for errors.
+    fn decode_line(
+        &self,
+        line: &[u8],
+        line_offset: usize,
+        metadata: &mut Header,
+        checksum: &mut Crc32,
+        decoded: &mut Vec<u8>,
+    ) -> Result<BodyLine, DecodeError> {
+        if line.starts_with(b"=ypart ") {
+            metadata.merge_part(
+                parse_header_line_with(line, self.strict, self.ignore_unknown_keywords)
+                    .map_err(at(line_offset))?,
+            );
+            Ok(BodyLine::Part)
+        } else if line.starts_with(b"=yend ") {
+            metadata.merge_footer(
+                parse_header_line_with(line, self.strict, self.ignore_unknown_keywords)
+                    .map_err(at(line_offset))?,
+            );
+            Ok(BodyLine::Footer)
+        } else if self.nntp_dot_termination && is_dot_line(line) {
+            Ok(BodyLine::Skipped)
+        } else {
+            decoded.resize(line.len(), 0);
+            let decoded_length = decode_buffer_into(line, decoded).map_err(at(line_offset))?;
+            self.check_output_size(checksum, decoded_length)?;
+            checksum.update_with_slice(&decoded[..decoded_length]);
+            Ok(BodyLine::Data(decoded_length))
+        }
+    }
+
+    /// Skips the lines of the stream before the resume offset, which were decoded before.
+    ///
+    /// The `=ypart` line is merged into `metadata`. The output is positioned after the bytes
+    /// of the block that were written before, and their checksum, read from the output file, is
+    /// returned.
+    fn skip_decoded<R>(
+        &self,
+        rdr: &mut R,
+        offset: &mut usize,
+        metadata: &mut Header,
+        output: &mut BufWriter<File>,
+        (stream_offset, output_offset): (u64, u64),
+    ) -> Result<Crc32, DecodeError>
+    where
+        R: BufRead,
+    {
+        let mut line_buf = Vec::<u8>::with_capacity(2 * DEFAULT_LINE_LENGTH as usize);
+        let mut footer_found = false;
+        while !footer_found && (*offset as u64) < stream_offset {
+            line_buf.clear();
+            let length = read_line(rdr, &mut line_buf)?;
+            if length == 0 {
+                return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+            }
+            let line_offset = *offset;
+            *offset += length;
+            if line_buf.starts_with(b"=ypart ") {
+                metadata.merge_part(
+                    parse_header_line_with(&line_buf, self.strict, self.ignore_unknown_keywords)
+                        .map_err(at(line_offset))?,
+                );
+            } else if line_buf.starts_with(b"=yend ") {
+                footer_found = true;
+            }
+        }
+        if footer_found || *offset as u64 != stream_offset {
+            let message = "resume offset is not the start of a data line";
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, message).into());
+        }
+
+        let file = output.get_mut();
+        file.seek(SeekFrom::Start(begin_position(
+            metadata.begin.unwrap_or(1),
+        )?))?;
+        let mut checksum = Crc32::new();
+        let mut buffer = [0u8; 8192];
+        let mut decoded = file.take(output_offset);
+        loop {
+            let length = decoded.read(&mut buffer)?;
+            if length == 0 {
+                break;
+            }
+            checksum.update_with_slice(&buffer[..length]);
+        }
+        if (checksum.num_bytes() as u64) < output_offset {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        Ok(checksum)
+    }
+
+    /// Checks that `length` more decoded bytes stay within `max_output_bytes`.
+    fn check_output_size(&self, checksum: &Crc32, length: usize) -> Result<(), DecodeError> {
+        match self.max_output_bytes {
+            Some(limit) if checksum.num_bytes() + length > limit => {
+                Err(DecodeError::OutputTooLarge { limit })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks the decoded body against the metadata.
+    /// Returns whether a checksum was present and matched.
+    fn check_body(&self, metadata: &Header, body: &Body) -> Result<bool, DecodeError> {
+        if self.strict && !body.footer_found {
+            return Err(DecodeError::MissingFooter);
+        }
+        verify(
+            metadata,
+            body.footer_found,
+            &body.checksum,
+            self.checksum_policy,
+        )
+    }
+
+    /// Determines the relative path of the output file from the raw filename in the header.
+    ///
+    /// The name is first trimmed and unquoted as described in `trim_filename`. Absolute paths
+    /// are rejected. Both `/` and `\` are treated as separators.
+    fn output_filename(&self, name: &[u8]) -> Result<PathBuf, DecodeError> {
+        let name = if self.trim_filename {
+            trim_ascii_whitespace(name)
+        } else {
+            name
+        };
+        let name = match name.strip_prefix(b"\"").and_then(|n| n.strip_suffix(b"\"")) {
+            Some(unquoted) => unquoted,
+            None => name,
+        };
+        if name.is_empty() {
+            return Err(DecodeError::MissingFilename);
+        }
+        let unsafe_filename = || DecodeError::UnsafeFilename {
+            name: String::from_utf8_lossy(name).to_string(),
+        };
+
+        let is_absolute = name.starts_with(b"/")
+            || name.starts_with(b"\\")
+            || name.get(1) == Some(&b':')
+            || Path::new(&os_string_from_bytes(name)).has_root();
+        if is_absolute {
+            return Err(unsafe_filename());
+        }
+
+        let components = name
+            .split(|&b| b == b'/' || b == b'\\')
+            .filter(|c| !c.is_empty() && *c != b".")
+            .collect::<Vec<&[u8]>>();
+        if self.allow_subdirs {
+            if components.is_empty() || components.contains(&&b".."[..]) {
+                return Err(unsafe_filename());
+            }
+            Ok(components.iter().map(|c| os_string_from_bytes(c)).collect())
+        } else {
+            match components.last() {
+                Some(&filename) if filename != b".." => {
+                    Ok(PathBuf::from(os_string_from_bytes(filename)))
+                }
+                _ => Err(unsafe_filename()),
+            }
+        }
+    }
+
+    /// In strict mode, reads the rest of the stream and checks that it contains only whitespace.
+    fn check_trailing_data<R>(&self, rdr: &mut R) -> Result<(), DecodeError>
+    where
+        R: BufRead,
+    {
+        if self.strict {
+            let mut trailing = Vec::new();
+            let bytes = rdr.read_to_end(&mut trailing)?;
+            if trailing.iter().any(|b| !b.is_ascii_whitespace()) {
+                return Err(DecodeError::TrailingData { bytes });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "async")]
+impl<P> DecodeOptions<P>
+where
+    P: AsRef<Path>,
+{
+    /// Decodes the data from an asynchronous stream to the specified directory.
+    ///
+    /// Performs the same parsing and checks as `decode_stream`, and writes the output with
+    /// `tokio::fs::File`. Only available with the `async` feature.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # async fn example() {
+    /// let decode_options = yenc::DecodeOptions::new("/tmp/decoded");
+    /// let input = tokio::fs::File::open("test2.bin.yenc").await.unwrap();
+    /// let result = decode_options.decode_async(input).await.unwrap();
+    /// # }
+    /// ```
+    /// # Errors
+    /// The same errors as `decode_stream`.
+    pub async fn decode_async<R>(&self, read_stream: R) -> Result<DecodeResult, DecodeError>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let mut rdr = tokio::io::BufReader::new(read_stream);
+        let mut offset = 0;
+        let mut metadata = self.read_first_header_async(&mut rdr, &mut offset).await?;
+
+        let (output_file, output) = self.open_output_async(&metadata).await?;
+        let result = self
+            .decode_body_async(&mut rdr, &mut offset, &mut metadata, output)
+            .await;
+        if result.is_err() && self.remove_on_error(&output_file) {
+            tokio::fs::remove_file(output_file.write_path()).await?;
+        }
+        let (checksum_ok, body) = result?;
+        if let Some(temp_path) = &output_file.temp_path {
+            tokio::fs::rename(temp_path, &output_file.path).await?;
+        }
+        self.check_trailing_data_async(&mut rdr).await?;
+
+        Ok(DecodeResult {
+            path: output_file.path,
+            name: metadata.name,
+            part: metadata.part,
+            total: metadata.total,
+            begin: metadata.begin,
+            end: metadata.end,
+            size: metadata.size,
+            crc32: metadata.crc32,
+            pcrc32: metadata.pcrc32,
+            checksum_ok,
+            computed_crc32: body.checksum.crc(),
+        })
+    }
+
+    /// Decodes the data from an asynchronous stream and writes the decoded bytes to the
+    /// asynchronous writer, like `decode_stream_to_writer`.
+    ///
+    /// Returns the parsed metadata. Only available with the `async` feature.
+    pub async fn decode_async_to_writer<R, W>(
+        &self,
+        read_stream: R,
+        output: W,
+    ) -> Result<Header, DecodeError>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let mut rdr = tokio::io::BufReader::new(read_stream);
+        let mut output = output;
+        let mut offset = 0;
+        let mut metadata = self.read_first_header_async(&mut rdr, &mut offset).await?;
+        let body = self
+            .decode_lines_async(
+                &mut rdr,
+                &mut offset,
+                &mut metadata,
+                &mut output,
+                ignore_part_begin_async,
+            )
+            .await?;
+        self.check_body(&metadata, &body)?;
+        self.check_trailing_data_async(&mut rdr).await?;
+        Ok(metadata)
+    }
+
+    /// Creates the output file for the block with the specified header, like `open_output`,
+    /// with `tokio::fs`.
+    async fn open_output_async(
+        &self,
+        metadata: &Header,
+    ) -> Result<(OutputFile, tokio::fs::File), DecodeError> {
+        let (output_pathbuf, temp_path, open_options) = self.output_target(metadata, false)?;
+        if let Some(parent) = output_pathbuf.parent() {
+            if self.allow_subdirs {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+        }
+        if temp_path.is_some()
+            && !self.may_replace()
+            && tokio::fs::metadata(&output_pathbuf).await.is_ok()
+        {
+            return Err(io::Error::from(io::ErrorKind::AlreadyExists).into());
+        }
+
+        let write_path = temp_path.as_deref().unwrap_or(&output_pathbuf);
+        let created = temp_path.is_some() || tokio::fs::metadata(write_path).await.is_err();
+        let mut output_file = tokio::fs::OpenOptions::from(open_options)
+            .open(write_path)
+            .await?;
+        if self.append {
+            output_file.seek(SeekFrom::End(0)).await?;
+        } else if let Some(size) = self.preallocated_size(metadata) {
+            output_file.set_len(size).await?;
+        }
+
+        let output = OutputFile {
+            path: output_pathbuf,
+            temp_path,
+            created,
+        };
+        Ok((output, output_file))
+    }
+
+    /// Decodes the body of a block to the output file, and checks it against the metadata.
+    /// Returns whether the checksum matched, and the decoded body.
+    async fn decode_body_async<R>(
+        &self,
+        rdr: &mut R,
+        offset: &mut usize,
+        metadata: &mut Header,
+        output: tokio::fs::File,
+    ) -> Result<(bool, Body), DecodeError>
+    where
+        R: AsyncBufRead + Unpin,
+    {
+        let mut output = tokio::io::BufWriter::new(output);
+        let body = self
+            .decode_lines_async(rdr, offset, metadata, &mut output, seek_to_begin_async)
+            .await?;
+        Ok((self.check_body(metadata, &body)?, body))
+    }
+
+    /// Decodes the lines following the `=ybegin` line up to and including the `=yend` line,
+    /// like `decode_lines`. When a `=ypart` line with a begin offset is read, the future
+    /// returned by `on_part_begin` is awaited.
+    async fn decode_lines_async<R, W, F>(
+        &self,
+        rdr: &mut R,
+        offset: &mut usize,
+        metadata: &mut Header,
+        output: &mut W,
+        mut on_part_begin: F,
+    ) -> Result<Body, DecodeError>
+    where
+        R: AsyncBufRead + Unpin,
+        W: AsyncWrite + Unpin,
+        F: for<'w> FnMut(&'w mut W, usize) -> PartBeginFuture<'w>,
+    {
+        let mut checksum = Crc32::new();
+        let mut line_buf = Vec::<u8>::with_capacity(2 * DEFAULT_LINE_LENGTH as usize);
+        let mut decoded = Vec::<u8>::with_capacity(2 * DEFAULT_LINE_LENGTH as usize);
+
+        let mut footer_found = false;
+        while !footer_found {
+            line_buf.clear();
+            let length = read_line_async(rdr, &mut line_buf).await?;
+            if length == 0 {
+                break;
+            }
+            let line_offset = *offset;
+            *offset += length;
+            match self.decode_line(
+                &line_buf,
+                line_offset,
+                metadata,
+                &mut checksum,
+                &mut decoded,
+            )? {
+                BodyLine::Part => {
+                    if let Some(begin) = metadata.begin {
+                        on_part_begin(output, begin).await?;
+                    }
+                }
+                BodyLine::Footer => footer_found = true,
+                BodyLine::Skipped => {}
+                BodyLine::Data(decoded_length) => {
+                    output.write_all(&decoded[..decoded_length]).await?;
+                }
+            }
+        }
+        output.flush().await?;
+
+        Ok(Body {
+            footer_found,
+            checksum,
+        })
+    }
+
+    /// In strict mode, reads the rest of the stream and checks that it contains only whitespace.
+    async fn check_trailing_data_async<R>(&self, rdr: &mut R) -> Result<(), DecodeError>
+    where
+        R: AsyncRead + Unpin,
+    {
+        if self.strict {
+            let mut trailing = Vec::new();
+            let bytes = rdr.read_to_end(&mut trailing).await?;
+            if trailing.iter().any(|b| !b.is_ascii_whitespace()) {
+                return Err(DecodeError::TrailingData { bytes });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Removes leading and trailing ASCII whitespace.
+fn trim_ascii_whitespace(bytes: &[u8]) -> &[u8] {
+    let start = bytes
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or(bytes.len());
+    let end = bytes
+        .iter()
+        .rposition(|b| !b.is_ascii_whitespace())
+        .map_or(start, |pos| pos + 1);
+    &bytes[start..end]
+}
+
+/// Converts raw filename bytes to an `OsString`.
+/// The bytes are kept as is on Unix, and converted lossy to UTF-8 elsewhere.
+#[cfg(unix)]
+fn os_string_from_bytes(bytes: &[u8]) -> OsString {
+    use std::os::unix::ffi::OsStrExt;
+    std::ffi::OsStr::from_bytes(bytes).to_os_string()
+}
+
+/// Converts raw filename bytes to an `OsString`.
+/// The bytes are kept as is on Unix, and converted lossy to UTF-8 elsewhere.
+#[cfg(not(unix))]
+fn os_string_from_bytes(bytes: &[u8]) -> OsString {
+    OsString::from(String::from_utf8_lossy(bytes).to_string())
+}
+
+/// Skips lines until the `=ybegin` line is found and parses it.
+/// With `strict`, a keyword that occurs more than once results in an error.
+/// With `ignore_unknown`, unknown keywords are skipped.
+/// With `tolerant_prefix`, a byte order mark, whitespace and quote markers before `=ybegin`
+/// are skipped.
+/// Returns `None` when the end of the stream is reached first.
+fn read_header<R>(
+    rdr: &mut R,
+    tolerant_prefix: bool,
+    strict: bool,
+    ignore_unknown: bool,
+    offset: &mut usize,
+) -> Result<Option<Header>, DecodeError>
+where
+    R: BufRead,
+{
+    loop {
+        let mut line_buf = Vec::<u8>::with_capacity(2 * DEFAULT_LINE_LENGTH as usize);
+        let length = read_line(rdr, &mut line_buf)?;
+        if length == 0 {
+            return Ok(None);
+        }
+        let line_offset = *offset;
+        *offset += length;
+        if let Some(header) = header_line(&line_buf, tolerant_prefix) {
+            return Ok(Some(
+                parse_header_line_with(header, strict, ignore_unknown).map_err(at(line_offset))?,
+            ));
+        }
+    }
+}
+
+/// Reads a line, including the line ending, and appends it to `buf`.
+///
+/// Like `read_until(LF, buf)`, but a CR that is not followed by LF also ends the line, so
+/// articles with CR-only line endings are split in lines as well. A CR never occurs unescaped
+/// in the encoded data. Returns the number of bytes read, 0 at the end of the stream.
+fn read_line<R>(rdr: &mut R, buf: &mut Vec<u8>) -> io::Result<usize>
+where
+    R: BufRead,
+{
+    let mut length = 0;
+    loop {
+        let available = rdr.fill_buf()?;
+        let (consumed, done) = scan_line(available, buf);
+        rdr.consume(consumed);
+        length += consumed;
+        if done || consumed == 0 {
+            return Ok(length);
+        }
+    }
+}
+
+/// Reads a line, like `read_line`, from an asynchronous reader.
+#[cfg(feature = "async")]
+async fn read_line_async<R>(rdr: &mut R, buf: &mut Vec<u8>) -> io::Result<usize>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut length = 0;
+    loop {
+        let available = rdr.fill_buf().await?;
+        let (consumed, done) = scan_line(available, buf);
+        rdr.consume(consumed);
+        length += consumed;
+        if done || consumed == 0 {
+            return Ok(length);
+        }
+    }
+}
+
+/// Appends the bytes up to and including the line ending from `available` to `buf`.
+/// Returns the number of bytes consumed, and whether the end of the line was reached.
+/// When `buf` ends with a CR, only a following LF is consumed.
+fn scan_line(available: &[u8], buf: &mut Vec<u8>) -> (usize, bool) {
+    if buf.last() == Some(&CR) {
+        return match available.first() {
+            Some(&LF) => {
+                buf.push(LF);
+                (1, true)
+            }
+            Some(_) => (0, true),
+            None => (0, false),
+        };
+    }
+    match available.iter().position(|&b| b == LF || b == CR) {
+        Some(idx) => {
+            buf.extend_from_slice(&available[..=idx]);
+            (idx + 1, available[idx] == LF)
+        }
+        None => {
+            buf.extend_from_slice(available);
+            (available.len(), false)
+        }
+    }
+}
+
+/// Wraps an error in a line in `DecodeError::At`, with the offset of the line in the stream.
+fn at(offset: usize) -> impl FnOnce(DecodeError) -> DecodeError {
+    move |err| DecodeError::At {
+        offset,
+        source: Box::new(err),
+    }
+}
+
+/// Returns whether the line is the NNTP end-of-article marker, a line with only a dot.
+fn is_dot_line(line: &[u8]) -> bool {
+    matches!(line, b"." | b".\n" | b".\r" | b".\r\n")
+}
+
+/// Returns the `=ybegin` line, when `line` is one, without the tolerated prefix.
+fn header_line(line: &[u8], tolerant_prefix: bool) -> Option<&[u8]> {
+    let line = match tolerant_prefix {
+        true => {
+            let line = line.strip_prefix(b"\xef\xbb\xbf").unwrap_or(line);
+            let start = line
+                .iter()
+                .position(|&b| !(b.is_ascii_whitespace() || b == b'>'))
+                .unwrap_or(line.len());
+            &line[start..]
+        }
+        false => line,
+    };
+    match line.starts_with(b"=ybegin ") {
+        true => Some(line),
+        false => None,
+    }
+}
+
+/// Skips lines until the `=ybegin` line is found and parses it, like `read_header`.
+#[cfg(feature = "async")]
+async fn read_header_async<R>(
+    rdr: &mut R,
+    tolerant_prefix: bool,
+    strict: bool,
+    ignore_unknown: bool,
+    offset: &mut usize,
+) -> Result<Option<Header>, DecodeError>
+where
+    R: AsyncBufRead + Unpin,
+{
+    loop {
+        let mut line_buf = Vec::<u8>::with_capacity(2 * DEFAULT_LINE_LENGTH as usize);
+        let length = read_line_async(rdr, &mut line_buf).await?;
+        if length == 0 {
+            return Ok(None);
+        }
+        let line_offset = *offset;
+        *offset += length;
+        if let Some(header) = header_line(&line_buf, tolerant_prefix) {
+            return Ok(Some(
+                parse_header_line_with(header, strict, ignore_unknown).map_err(at(line_offset))?,
+            ));
+        }
+    }
+}
+
+/// Seeks the output file to the begin offset of the part.
+fn seek_to_begin<W>(output: &mut W, begin: usize) -> Result<(), DecodeError>
+where
+    W: Write + Seek,
+{
+    output.seek(SeekFrom::Start(begin_position(begin)?))?;
+    Ok(())
+}
+
+/// Seeks the asynchronous output file to the begin offset of the part, like `seek_to_begin`.
+#[cfg(feature = "async")]
+fn seek_to_begin_async<W>(output: &mut W, begin: usize) -> PartBeginFuture<'_>
+where
+    W: AsyncWrite + AsyncSeek + Unpin + Send,
+{
+    Box::pin(async move {
+        output.seek(SeekFrom::Start(begin_position(begin)?)).await?;
+        Ok(())
+    })
+}
+
+/// Keeps writing the decoded bytes of a part sequentially, for outputs that can't seek.
+#[cfg(feature = "async")]
+fn ignore_part_begin_async<W>(_output: &mut W, _begin: usize) -> PartBeginFuture<'_> {
+    Box::pin(async { Ok(()) })
+}
+
+/// Returns the file position of the begin offset of a part, which starts at 1.
+fn begin_position(begin: usize) -> Result<u64, DecodeError> {
+    match begin.checked_sub(1) {
+        Some(position) => Ok(position as u64),
+        None => {
+            let message = "part begin offset is 0";
+            Err(io::Error::new(io::ErrorKind::InvalidData, message).into())
+        }
+    }
+}
+
+/// Returns the expected number of decoded bytes, if known.
+fn expected_size(metadata: &Header) -> Option<usize> {
+    // for a part, `size` is the size of the complete file, so the part range is used instead
+    match (metadata.begin, metadata.end) {
+        (Some(begin), Some(end)) => Some(end - begin + 1),
+        _ if metadata.part.is_none() => metadata.size,
+        _ => None,
+    }
+}
+
+/// Checks the checksum (when the footer was found) and the size of the part against the metadata.
+/// The size of a part is determined from its begin and end offset, the size of a
+/// single part article from the `size` field.
+/// Returns whether a checksum was present and matched.
+fn verify(
+    metadata: &Header,
+    footer_found: bool,
+    checksum: &Crc32,
+    checksum_policy: ChecksumPolicy,
+) -> Result<bool, DecodeError> {
+    let crc = checksum.crc();
+    let num_bytes = checksum.num_bytes();
+    let mut checksum_ok = false;
+    if footer_found {
+        if let Some(expected_crc) = metadata.pcrc32.or(metadata.crc32) {
+            checksum_ok = expected_crc == crc;
+            if !checksum_ok && checksum_policy == ChecksumPolicy::Fail {
+                return Err(DecodeError::InvalidChecksum);
+            }
+        }
+    }
+
+    if let Some(expected_size) = expected_size(metadata) {
+        if expected_size != num_bytes {
+            return Err(DecodeError::IncompleteData {
+                expected_size,
+                actual_size: num_bytes,
+            });
+        }
+    }
+    Ok(checksum_ok)
+}
+
+/// Streaming decoder, that yields the decoded data line by line.
+///
+/// The `=ybegin` line is parsed on the first call to `next`, after which the parsed
+/// header is available through `header()`. Every following item contains the decoded data
+/// of one line, until the `=yend` line is read. When the checksum or size in the footer does
+/// not match the decoded data, the last item is an error. When the input contains no
+/// `=ybegin` line, the only item is `DecodeError::NoYencData`.
+///
+/// # Example
+/// ```rust,no_run
+/// use std::io::BufReader;
+/// let input = BufReader::new(std::fs::File::open("test2.bin.yenc").unwrap());
+/// let mut decoder = yenc::Decoder::new(input);
+/// let mut decoded = Vec::new();
+/// for chunk in &mut decoder {
+///     decoded.extend(chunk.unwrap());
+/// }
+/// println!("{:?}", decoder.header());
+/// ```
+#[derive(Debug)]
+pub struct Decoder<R> {
+    rdr: R,
+    header: Option<Header>,
+    checksum: Crc32,
+    offset: usize,
+    done: bool,
+}
+
+impl<R> Decoder<R>
+where
+    R: BufRead,
+{
+    /// Constructs a new Decoder reading from the specified reader.
+    pub fn new(rdr: R) -> Decoder<R> {
+        Decoder {
+            rdr,
+            header: None,
+            checksum: Crc32::new(),
+            offset: 0,
+            done: false,
+        }
+    }
+
+    /// Returns the parsed header, once the `=ybegin` line has been read.
+    /// The fields of the `=ypart` and `=yend` lines are added as they are read.
+    pub fn header(&self) -> Option<&Header> {
+        self.header.as_ref()
+    }
+
+    fn next_chunk(&mut self) -> Result<Option<Vec<u8>>, DecodeError> {
+        let metadata = match self.header {
+            Some(ref mut metadata) => metadata,
+            None => self.header.insert(
+                read_header(&mut self.rdr, false, false, false, &mut self.offset)?
+                    .ok_or(DecodeError::NoYencData)?,
+            ),
+        };
+
+        loop {
+            let mut line_buf = Vec::<u8>::with_capacity(2 * DEFAULT_LINE_LENGTH as usize);
+            let length = read_line(&mut self.rdr, &mut line_buf)?;
+            if length == 0 {
+                verify(metadata, false, &self.checksum, ChecksumPolicy::Fail)?;
+                return Ok(None);
+            }
+            let line_offset = self.offset;
+            self.offset += length;
+            if line_buf.starts_with(b"=ypart ") {
+                metadata.merge_part(parse_header_line(&line_buf).map_err(at(line_offset))?);
+            } else if line_buf.starts_with(b"=yend ") {
+                metadata.merge_footer(parse_header_line(&line_buf).map_err(at(line_offset))?);
+                verify(metadata, true, &self.checksum, ChecksumPolicy::Fail)?;
+                return Ok(None);
+            } else {
+                let decoded = decode_buffer(&line_buf[0..length]).map_err(at(line_offset))?;
+                if !decoded.is_empty() {
+                    self.checksum.update_with_slice(&decoded);
+                    return Ok(Some(decoded));
+                }
+            }
+        }
+    }
+}
+
+impl<R> Iterator for Decoder<R>
+where
+    R: BufRead,
+{
+    type Item = Result<Vec<u8>, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let result = self.next_chunk();
+        match result {
+            Ok(Some(decoded)) => Some(Ok(decoded)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(err) => {
+                self.done = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// A reader that yields the decoded data of a yEnc block.
+///
+/// The `=ybegin` and `=ypart` lines are parsed on the first call to `read`, after which the
+/// parsed header is available through `header()`. The decoded data is returned until the
+/// `=yend` line is read, after which `read` returns 0 (EOF). When the checksum or size in the
+/// footer does not match the decoded data, or the input contains no `=ybegin` line
+/// (`DecodeError::NoYencData`), `read` returns an error of kind `InvalidData`.
+///
+/// # Example
+/// ```rust,no_run
+/// use std::io::BufReader;
+/// let input = BufReader::new(std::fs::File::open("test2.bin.yenc").unwrap());
+/// let mut reader = yenc::DecodeReader::new(input);
+/// let mut output = std::fs::File::create("test2.bin").unwrap();
+/// std::io::copy(&mut reader, &mut output).unwrap();
+/// assert!(reader.checksum_ok());
+/// ```
+#[derive(Debug)]
+pub struct DecodeReader<R> {
+    decoder: Decoder<R>,
+    chunk: Vec<u8>,
+    pos: usize,
+    eof: bool,
+}
+
+impl<R> DecodeReader<R>
+where
+    R: BufRead,
+{
+    /// Constructs a new DecodeReader reading from the specified reader.
+    pub fn new(rdr: R) -> DecodeReader<R> {
+        DecodeReader {
+            decoder: Decoder::new(rdr),
+            chunk: Vec::new(),
+            pos: 0,
+            eof: false,
+        }
+    }
+
+    /// Returns the parsed header, once the `=ybegin` line has been read.
+    pub fn header(&self) -> Option<&Header> {
+        self.decoder.header()
+    }
+
+    /// Returns whether the checksum in the footer matches the decoded data.
+    ///
+    /// Returns false before EOF is reached, or when the footer has no checksum.
+    pub fn checksum_ok(&self) -> bool {
+        let expected_crc = self
+            .decoder
+            .header()
+            .and_then(|metadata| metadata.pcrc32.or(metadata.crc32));
+        self.eof && expected_crc == Some(self.decoder.checksum.crc())
+    }
+}
+
+impl<R> Read for DecodeReader<R>
+where
+    R: BufRead,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.chunk.len() {
+            match self.decoder.next() {
+                Some(Ok(chunk)) => {
+                    self.chunk = chunk;
+                    self.pos = 0;
+                }
+                Some(Err(err)) => return Err(err.into()),
+                None => {
+                    self.eof = true;
+                    return Ok(0);
+                }
+            }
+        }
+        let length = buf.len().min(self.chunk.len() - self.pos);
+        buf[..length].copy_from_slice(&self.chunk[self.pos..self.pos + length]);
+        self.pos += length;
+        Ok(length)
+    }
+}
+
+/// Decoder for encoded data that arrives in chunks of any size, for example network packets.
+///
+/// Unlike `decode_buffer`, an escape character at the end of a chunk, or a dot at the start of
+/// a line that may be doubled in the next chunk, are remembered between calls of `feed`, so
+/// the output does not depend on where the data is split. The input is the encoded data
+/// only, without the `=ybegin`, `=ypart` and `=yend` lines.
+///
+/// # Example
+/// ```rust
+/// let mut decoder = yenc::StreamDecoder::new();
+/// let mut decoded = Vec::new();
+/// decoder.feed(b"\xa3o=", &mut decoded).unwrap();
+/// decoder.feed(b"}\x98\x8d\r\n", &mut decoded).unwrap();
+/// assert_eq!(b"yE\x13nc", decoded.as_slice());
+/// assert_eq!(yenc::crc32(&decoded), decoder.finish());
+/// ```
+#[derive(Clone, Debug)]
+pub struct StreamDecoder {
+    pending_escape: bool,
+    at_line_start: bool,
+    // a dot was decoded at the start of the line, a second dot is skipped
+    pending_dot: bool,
+    crc: Crc32,
+}
+
+impl Default for StreamDecoder {
+    fn default() -> Self {
+        StreamDecoder {
+            pending_escape: false,
+            at_line_start: true,
+            pending_dot: false,
+            crc: Crc32::new(),
+        }
+    }
+}
 
-            let mut output = BufWriter::new(output_file);
+impl StreamDecoder {
+    /// Constructs a new StreamDecoder, at the start of a line.
+    pub fn new() -> StreamDecoder {
+        Default::default()
+    }
 
-            let mut footer_found = false;
-            while !footer_found {
-                let mut line_buf = Vec::<u8>::with_capacity(2 * DEFAULT_LINE_SIZE as usize);
-                let length = rdr.read_until(LF, &mut line_buf)?;
-                if length == 0 {
-                    break;
-                }
-                if line_buf.starts_with(b"=ypart ") {
-                    let part_metadata = parse_header_line(&line_buf)?;
-                    metadata.begin = part_metadata.begin;
-                    metadata.end = part_metadata.end;
-                    if let Some(begin) = metadata.begin {
-                        output.seek(SeekFrom::Start((begin - 1) as u64))?;
-                    }
-                } else if line_buf.starts_with(b"=yend ") {
-                    footer_found = true;
-                    let mm = parse_header_line(&line_buf)?;
-                    metadata.size = mm.size;
-                    metadata.crc32 = mm.crc32;
-                    metadata.pcrc32 = mm.pcrc32;
-                } else {
-                    let decoded = decode_buffer(&line_buf[0..length])?;
-                    checksum.update(&decoded);
-                    num_bytes += decoded.len();
-                    output.write_all(&decoded)?;
-                }
-            }
-            if footer_found {
-                if let Some(expected_part_crc) = metadata.pcrc32 {
-                    if expected_part_crc != checksum.finalize() {
-                        return Err(DecodeError::InvalidChecksum);
-                    }
-                } else if let Some(expected_crc) = metadata.crc32 {
-                    if expected_crc != checksum.finalize() {
-                        return Err(DecodeError::InvalidChecksum);
-                    }
+    /// Decodes the chunk and appends the decoded bytes to `out`.
+    ///
+    /// # Errors
+    /// - `DecodeError::DanglingEscape` when an escape character is followed by CR or LF; the
+    ///   bytes decoded before it are appended to `out` and added to the checksum
+    pub fn feed(&mut self, input: &[u8], out: &mut Vec<u8>) -> Result<(), DecodeError> {
+        let start = out.len();
+        out.reserve(input.len());
+        for &byte in input {
+            let pending_dot = self.pending_dot;
+            self.pending_dot = false;
+            if self.pending_escape {
+                if byte == CR || byte == LF {
+                    self.crc.update_with_slice(&out[start..]);
+                    return Err(DecodeError::DanglingEscape);
                 }
+                self.pending_escape = false;
+                self.at_line_start = false;
+                out.push(byte.wrapping_sub(ESCAPE_OFFSET).wrapping_sub(OFFSET));
+                continue;
             }
-
-            if let Some(end) = metadata.end {
-                if let Some(begin) = metadata.begin {
-                    let expected_size = end - begin + 1;
-                    if expected_size != num_bytes {
-                        return Err(DecodeError::IncompleteData {
-                            expected_size,
-                            actual_size: num_bytes,
-                        });
-                    }
+            match byte {
+                NUL | CR => {}
+                LF => self.at_line_start = true,
+                DOT if pending_dot => {}
+                ESCAPE => self.pending_escape = true,
+                _ => {
+                    self.pending_dot = byte == DOT && self.at_line_start;
+                    self.at_line_start = false;
+                    out.push(byte.wrapping_sub(OFFSET));
                 }
             }
         }
-        Ok(output_pathbuf.into_boxed_path())
+        self.crc.update_with_slice(&out[start..]);
+        Ok(())
+    }
+
+    /// The checksum and the number of bytes of the data decoded so far.
+    pub fn checksum(&self) -> &Crc32 {
+        &self.crc
+    }
+
+    /// Returns the CRC32 checksum of all decoded data.
+    ///
+    /// A pending escape character at the end of the last chunk is ignored, like `decode_buffer`
+    /// does.
+    pub fn finish(self) -> u32 {
+        self.crc.crc()
     }
 }
 
 /// Decode the encoded byte slice into a vector of bytes.
 ///
 /// Carriage Return (CR) and Line Feed (LF) are ignored.
+/// A doubled dot at the start of a line (NNTP dot-stuffing) is decoded as a single dot.
+///
+/// # Errors
+/// - `DecodeError::DanglingEscape` when an escape character is followed by CR or LF
 pub fn decode_buffer(input: &[u8]) -> Result<Vec<u8>, DecodeError> {
-    let mut output = Vec::<u8>::with_capacity(input.len());
-    let mut iter = input.iter().cloned().enumerate();
-    while let Some((col, byte)) = iter.next() {
-        let mut result_byte = byte;
-        match byte {
-            NUL | CR | LF => {
-                // for now, just continue
-                continue;
-            }
-            DOT if col == 0 => match iter.next() {
-                Some((_, DOT)) => {}
-                Some((_, b)) => {
-                    output.push(byte.overflowing_sub(42).0);
-                    result_byte = b;
-                }
-                None => {}
-            },
-            ESCAPE => {
-                match iter.next() {
-                    Some((_, b)) => {
-                        result_byte = b.overflowing_sub(64).0;
-                    }
-                    None => {
-                        // for now, just continue
-                        continue;
-                    }
-                }
-            }
-            _ => {}
+    let mut output = vec![0u8; input.len()];
+    let length = decode_buffer_into(input, &mut output)?;
+    output.truncate(length);
+    Ok(output)
+}
+
+/// Decodes the encoded byte slice into a vector of bytes, except for an escape character at the
+/// end of the slice, of which the escaped byte is in the next chunk.
+///
+/// Returns the decoded bytes and the number of consumed input bytes. When the input ends with
+/// a lone escape character, it is not consumed, and should be prepended to the next chunk.
+///
+/// # Example
+/// ```rust
+/// let (decoded, consumed) = yenc::decode_buffer_partial(b"\xa3o=").unwrap();
+/// assert_eq!(b"yE", decoded.as_slice());
+/// assert_eq!(2, consumed);
+/// let (decoded, consumed) = yenc::decode_buffer_partial(b"=}").unwrap();
+/// assert_eq!(b"\x13", decoded.as_slice());
+/// assert_eq!(2, consumed);
+/// ```
+/// # Errors
+/// - `DecodeError::DanglingEscape` when an escape character is followed by CR or LF
+pub fn decode_buffer_partial(input: &[u8]) -> Result<(Vec<u8>, usize), DecodeError> {
+    let mut consumed = 0;
+    while consumed < input.len() {
+        match input[consumed] {
+            ESCAPE if consumed + 1 == input.len() => break,
+            ESCAPE => consumed += 2,
+            _ => consumed += 1,
         }
-        output.push(result_byte.overflowing_sub(42).0);
     }
-    Ok(output)
+    Ok((decode_buffer(&input[..consumed])?, consumed))
+}
+
+/// Decodes a complete yEnc message in memory.
+///
+/// Parses the `=ybegin`, `=ypart` and `=yend` lines, and returns the decoded data together
+/// with the metadata. The checksum and size are checked like `DecodeOptions::decode_stream`
+/// does with the default options.
+///
+/// # Example
+/// ```rust
+/// let (data, header) = yenc::decode_message(b"=ybegin line=128 size=4 name=a.bin\r\n\xa3o\x98\x8d\r\n=yend size=4 crc32=6ee709ea\r\n").unwrap();
+/// assert_eq!(b"yEnc", data.as_slice());
+/// assert_eq!(Some("a.bin"), header.name());
+/// ```
+/// # Errors
+/// - `DecodeError::NoYencData` when the input contains no `=ybegin` line
+/// - `DecodeError::InvalidChecksum` when the checksum does not match the decoded data
+/// - `DecodeError::IncompleteData` when fewer or more bytes were decoded than expected
+pub fn decode_message(input: &[u8]) -> Result<(Vec<u8>, Header), DecodeError> {
+    let mut output = Vec::with_capacity(input.len());
+    let header = DecodeOptions::new(PathBuf::new()).decode_stream_to_writer(input, &mut output)?;
+    Ok((output, header))
+}
+
+/// Encodes the data with headers, decodes it again with `decode_message`, and checks that the
+/// decoded data and its CRC32 checksum match the original data.
+///
+/// Useful as a self-check, for example in the tests of code that builds archives with this
+/// crate.
+///
+/// # Example
+/// ```rust
+/// assert!(yenc::verify_roundtrip(b"yEnc roundtrip", 128).is_ok());
+/// ```
+/// # Errors
+/// - `DecodeError::IncompleteData` when the decoded data has a different length
+/// - `DecodeError::InvalidChecksum` when the decoded data or its checksum differs
+/// - `DecodeError::IoError` of kind `InvalidData` when encoding fails, for example for a line
+///   length of 0
+pub fn verify_roundtrip(original: &[u8], line_length: u16) -> Result<(), DecodeError> {
+    let encoded = EncodeOptions::new()
+        .line_length(line_length)
+        .encode_bytes(original, "roundtrip.bin")
+        .map_err(io::Error::from)?;
+    let (decoded, header) = decode_message(&encoded)?;
+    if decoded.len() != original.len() {
+        return Err(DecodeError::IncompleteData {
+            expected_size: original.len(),
+            actual_size: decoded.len(),
+        });
+    }
+    if decoded != original || header.crc32 != Some(crc32(original)) {
+        return Err(DecodeError::InvalidChecksum);
+    }
+    Ok(())
+}
+
+/// Parses a `=ybegin`, `=ypart` or `=yend` line.
+///
+/// Can be used to inspect the header of an article before decoding the body.
+///
+/// The `name` keyword must be the last keyword in the line, as the specification requires:
+/// the rest of the line after `name=` is the filename, also when it contains `=` characters
+/// or what looks like other keywords.
+///
+/// A part range with an `end` offset smaller than the `begin` offset, or data after the LF
+/// that ends the line, results in `DecodeError::InvalidHeader`.
+///
+/// # Example
+/// ```rust
+/// let header = yenc::parse_header(b"=ybegin part=1 total=2 line=128 size=1000 name=a.bin\r\n").unwrap();
+/// assert_eq!(Some("a.bin"), header.name());
+/// assert_eq!(Some(2), header.total());
+/// ```
+pub fn parse_header(line: &[u8]) -> Result<Header, DecodeError> {
+    parse_header_line(line)
+}
+
+fn parse_header_line(line_buf: &[u8]) -> Result<Header, DecodeError> {
+    parse_header_line_with(line_buf, false, false)
 }
 
-fn parse_header_line(line_buf: &[u8]) -> Result<MetaData, DecodeError> {
+/// Parses a `=ybegin`, `=ypart` or `=yend` line.
+/// When a keyword occurs more than once, the last value is used, or, with `strict`,
+/// `DecodeError::DuplicateKeyword` is returned.
+/// An unknown keyword results in `DecodeError::InvalidHeader`, or, with `ignore_unknown`, its
+/// value is skipped up to the next space or the end of the line.
+fn parse_header_line_with(
+    line_buf: &[u8],
+    strict: bool,
+    ignore_unknown: bool,
+) -> Result<Header, DecodeError> {
     #[derive(Debug)]
     enum State {
         Keyword,
         Value,
+        Skip,
         End,
     }
 
+    // a line without LF (CR-only line ending, or at the end of the stream) is parsed as if it
+    // ends with LF, so the last value is complete
+    let terminated;
+    let line_buf = match line_buf.last() {
+        Some(&LF) => line_buf,
+        _ => {
+            terminated = [line_buf, b"\n"].concat();
+            &terminated[..]
+        }
+    };
+
     let header_line = String::from_utf8_lossy(line_buf).to_string();
     if !(header_line.starts_with("=ybegin ")
         || header_line.starts_with("=yend ")
@@ -210,8 +2367,6 @@ fn parse_header_line(line_buf: &[u8]) -> Result<MetaData, DecodeError> {
         });
     }
 
-    let is_yend = header_line.starts_with("=yend ");
-
     let offset = match line_buf.iter().position(|&c| c == b' ') {
         Some(pos) => pos + 1,
         None => {
@@ -222,18 +2377,36 @@ fn parse_header_line(line_buf: &[u8]) -> Result<MetaData, DecodeError> {
         }
     };
 
-    let mut metadata: MetaData = Default::default();
+    // the line ends at the first LF, data after it is not part of the header line
+    if let Some(pos) = line_buf.iter().position(|&c| c == LF) {
+        if pos + 1 < line_buf.len() {
+            return Err(DecodeError::InvalidHeader {
+                line: header_line,
+                position: pos + 1,
+            });
+        }
+    }
+
+    let mut metadata: Header = Default::default();
     let mut state = State::Keyword;
 
     let mut keyword: &[u8] = &[];
     let mut keyword_start_idx: Option<usize> = None;
     let mut value: &[u8] = &[];
     let mut value_start_idx: Option<usize> = None;
+    let mut seen_keywords: Vec<&[u8]> = Vec::new();
+    // the position of the last `begin` or `end` value, for an error in the part range
+    let mut range_position = 0;
 
     for (i, &c) in line_buf[offset..].iter().enumerate() {
         let position = i + offset;
         match state {
-            State::End => unreachable!(),
+            State::End => {
+                return Err(DecodeError::InvalidHeader {
+                    line: header_line,
+                    position,
+                })
+            }
             State::Keyword => match c {
                 b'a'..=b'z' | b'0'..=b'9' => {
                     if keyword_start_idx.is_none() {
@@ -250,16 +2423,25 @@ fn parse_header_line(line_buf: &[u8]) -> Result<MetaData, DecodeError> {
                     };
                 }
                 b'=' => {
-                    if keyword.is_empty() || !is_known_keyword(keyword) {
+                    if ignore_unknown && !keyword.is_empty() && !is_known_keyword(keyword) {
+                        state = State::Skip;
+                    } else if keyword.is_empty() || !is_known_keyword(keyword) {
                         return Err(DecodeError::InvalidHeader {
                             line: header_line,
                             position,
                         });
+                    } else if strict && seen_keywords.contains(&keyword) {
+                        return Err(DecodeError::DuplicateKeyword {
+                            keyword: String::from_utf8_lossy(keyword).to_string(),
+                        });
                     } else {
+                        seen_keywords.push(keyword);
+                        value = &[];
                         state = State::Value;
                     }
                 }
-                CR | LF => {}
+                // extra whitespace between keyword=value pairs and before `=` is ignored
+                SPACE | CR | LF => {}
                 _ => {
                     return Err(DecodeError::InvalidHeader {
                         line: header_line,
@@ -267,12 +2449,21 @@ fn parse_header_line(line_buf: &[u8]) -> Result<MetaData, DecodeError> {
                     });
                 }
             },
+            State::Skip => match c {
+                SPACE => {
+                    state = State::Keyword;
+                    keyword_start_idx = None;
+                }
+                LF => state = State::End,
+                _ => {}
+            },
             State::Value => match keyword {
                 b"name" => match c {
                     CR => {}
                     LF => {
                         state = State::End;
                         metadata.name = Some(String::from_utf8_lossy(value).to_string());
+                        metadata.name_bytes = Some(value.to_vec());
                     }
                     _ => {
                         if value_start_idx.is_none() {
@@ -304,31 +2495,15 @@ fn parse_header_line(line_buf: &[u8]) -> Result<MetaData, DecodeError> {
                             }
                         };
                     }
-                    SPACE => {
+                    SPACE | LF | CR => {
                         metadata.size = match String::from_utf8_lossy(value).parse::<usize>() {
                             Ok(size) => Some(size),
-                            Err(_) => {
-                                return Err(DecodeError::InvalidHeader {
-                                    line: header_line,
-                                    position,
-                                })
-                            }
+                            Err(err) => return Err(size_error(&err, value, header_line, position)),
                         };
                         state = State::Keyword;
                         keyword_start_idx = None;
                         value_start_idx = None;
                     }
-                    LF | CR if is_yend => {
-                        metadata.size = match String::from_utf8_lossy(value).parse::<usize>() {
-                            Ok(size) => Some(size),
-                            Err(_) => {
-                                return Err(DecodeError::InvalidHeader {
-                                    line: header_line,
-                                    position,
-                                })
-                            }
-                        };
-                    }
                     _ => {
                         return Err(DecodeError::InvalidHeader {
                             line: header_line,
@@ -354,19 +2529,22 @@ fn parse_header_line(line_buf: &[u8]) -> Result<MetaData, DecodeError> {
                     SPACE | LF | CR => {
                         let nr = match String::from_utf8_lossy(value).parse::<usize>() {
                             Ok(size) => Some(size),
-                            Err(_) => {
+                            Err(err) => return Err(size_error(&err, value, header_line, position)),
+                        };
+
+                        if keyword == b"begin" {
+                            // offsets start at 1
+                            if nr == Some(0) {
                                 return Err(DecodeError::InvalidHeader {
                                     line: header_line,
                                     position,
-                                })
+                                });
                             }
-                        };
-
-                        if keyword == b"begin" {
                             metadata.begin = nr;
                         } else {
                             metadata.end = nr;
                         }
+                        range_position = position;
                         state = State::Keyword;
                         keyword_start_idx = None;
                         value_start_idx = None;
@@ -393,7 +2571,7 @@ fn parse_header_line(line_buf: &[u8]) -> Result<MetaData, DecodeError> {
                             }
                         };
                     }
-                    SPACE => {
+                    SPACE | LF | CR => {
                         metadata.line_length = match String::from_utf8_lossy(value).parse() {
                             Ok(size) => Some(size),
                             Err(_) => {
@@ -429,7 +2607,7 @@ fn parse_header_line(line_buf: &[u8]) -> Result<MetaData, DecodeError> {
                             }
                         };
                     }
-                    SPACE => {
+                    SPACE | LF | CR => {
                         let number = match String::from_utf8_lossy(value).parse::<u32>() {
                             Ok(size) => Some(size),
                             Err(_) => {
@@ -448,19 +2626,6 @@ fn parse_header_line(line_buf: &[u8]) -> Result<MetaData, DecodeError> {
                         keyword_start_idx = None;
                         value_start_idx = None;
                     }
-                    LF | CR if is_yend && keyword == b"part" => {
-                        let number = match String::from_utf8_lossy(value).parse::<u32>() {
-                            Ok(size) => Some(size),
-                            Err(_) => {
-                                return Err(DecodeError::InvalidHeader {
-                                    line: header_line,
-                                    position,
-                                })
-                            }
-                        };
-
-                        metadata.part = number;
-                    }
                     _ => {
                         return Err(DecodeError::InvalidHeader {
                             line: header_line,
@@ -518,9 +2683,29 @@ fn parse_header_line(line_buf: &[u8]) -> Result<MetaData, DecodeError> {
             },
         };
     }
+    if let (Some(begin), Some(end)) = (metadata.begin, metadata.end) {
+        if end < begin {
+            return Err(DecodeError::InvalidHeader {
+                line: header_line,
+                position: range_position,
+            });
+        }
+    }
     Ok(metadata)
 }
 
+/// Returns the error for a `size`, `begin` or `end` value that cannot be parsed:
+/// `DecodeError::SizeOverflow` when the value is too large for `usize`, which can happen on a
+/// 32-bit platform, otherwise `DecodeError::InvalidHeader`.
+fn size_error(err: &ParseIntError, value: &[u8], line: String, position: usize) -> DecodeError {
+    match err.kind() {
+        IntErrorKind::PosOverflow => DecodeError::SizeOverflow {
+            value: String::from_utf8_lossy(value).to_string(),
+        },
+        _ => DecodeError::InvalidHeader { line, position },
+    }
+}
+
 fn is_known_keyword(keyword_slice: &[u8]) -> bool {
     matches!(
         keyword_slice,
@@ -531,7 +2716,12 @@ fn is_known_keyword(keyword_slice: &[u8]) -> bool {
 #[cfg(test)]
 #[allow(clippy::unreadable_literal)]
 mod tests {
-    use super::{decode_buffer, parse_header_line};
+    use super::{
+        begin_position, decode_buffer, decode_buffer_partial, header_line, parse_header,
+        parse_header_line, parse_header_line_with, verify_roundtrip, DecodeOptions, StreamDecoder,
+    };
+    use crate::errors::DecodeError;
+    use std::path::PathBuf;
 
     #[test]
     fn parse_valid_footer_end_nl() {
@@ -601,6 +2791,133 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_valid_header_numbers_end_nl() {
+        let metadata = parse_header_line(b"=ybegin size=100 line=128\n").unwrap();
+        assert_eq!(Some(128), metadata.line_length);
+        assert_eq!(Some(100), metadata.size);
+
+        let metadata = parse_header_line(b"=ybegin line=128 size=100\r\n").unwrap();
+        assert_eq!(Some(100), metadata.size);
+
+        let metadata = parse_header_line(b"=ybegin part=1 total=10\n").unwrap();
+        assert_eq!(Some(1), metadata.part);
+        assert_eq!(Some(10), metadata.total);
+
+        let metadata = parse_header_line(b"=yend size=100 total=10\r\n").unwrap();
+        assert_eq!(Some(10), metadata.total);
+    }
+
+    #[test]
+    fn parse_valid_header_extra_spaces() {
+        let metadata =
+            parse_header_line(b"=ybegin part=1  line=128   size=100 name=a.bin\r\n").unwrap();
+        assert_eq!(Some(1), metadata.part);
+        assert_eq!(Some(128), metadata.line_length);
+        assert_eq!(Some(100), metadata.size);
+        assert_eq!(Some("a.bin".to_string()), metadata.name);
+
+        let metadata = parse_header_line(b"=ypart  begin=1 end =100 \r\n").unwrap();
+        assert_eq!(Some(1), metadata.begin);
+        assert_eq!(Some(100), metadata.end);
+
+        let metadata = parse_header_line(b"=yend size=100 crc32=ded29f4f \n").unwrap();
+        assert_eq!(Some(0xded29f4f), metadata.crc32);
+    }
+
+    #[test]
+    fn parse_duplicate_keyword() {
+        let line = b"=yend size=100 crc32=ded29f4f crc32=00000000\r\n";
+        let metadata = parse_header_line_with(line, false, false).unwrap();
+        assert_eq!(Some(0), metadata.crc32);
+        assert_eq!(
+            Err(DecodeError::DuplicateKeyword {
+                keyword: "crc32".to_string()
+            }),
+            parse_header_line_with(line, true, false)
+        );
+    }
+
+    #[test]
+    fn parse_inverted_part_range() {
+        let line = b"=ypart begin=5 end=2\r\n";
+        assert_eq!(
+            Err(DecodeError::InvalidHeader {
+                line: String::from_utf8_lossy(line).to_string(),
+                position: 20,
+            }),
+            parse_header_line(line)
+        );
+        assert!(parse_header_line(b"=ypart begin=5 end=5\r\n").is_ok());
+    }
+
+    #[test]
+    fn parse_embedded_line_feed() {
+        let line = b"=ybegin name=x\nline=1\n";
+        assert_eq!(
+            Err(DecodeError::InvalidHeader {
+                line: String::from_utf8_lossy(line).to_string(),
+                position: 15,
+            }),
+            parse_header_line(line)
+        );
+        assert!(crate::parse_header(b"=ybegin size=1\nline=1\n").is_err());
+        assert!(crate::parse_header(b"=ybegin line=1 name=x\n").is_ok());
+    }
+
+    #[test]
+    fn parse_begin_zero() {
+        let line = b"=ypart begin=0 end=2\r\n";
+        assert_eq!(
+            Err(DecodeError::InvalidHeader {
+                line: String::from_utf8_lossy(line).to_string(),
+                position: 14,
+            }),
+            parse_header_line(line)
+        );
+        assert!(begin_position(0).is_err());
+        assert_eq!(Ok(0), begin_position(1));
+    }
+
+    #[test]
+    fn parse_size_overflow() {
+        let too_large = format!("{}0", usize::MAX);
+        assert_eq!(
+            Err(DecodeError::SizeOverflow {
+                value: too_large.clone()
+            }),
+            parse_header_line(format!("=ybegin line=128 size={} name=a\r\n", too_large).as_bytes())
+        );
+        assert_eq!(
+            Err(DecodeError::SizeOverflow {
+                value: too_large.clone()
+            }),
+            parse_header_line(format!("=ypart begin=1 end={}\r\n", too_large).as_bytes())
+        );
+        assert!(matches!(
+            parse_header_line(b"=ybegin line=128 size= name=a\r\n"),
+            Err(DecodeError::InvalidHeader { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_empty_name() {
+        let metadata = parse_header_line(b"=ybegin line=128 size=100 name=\r\n").unwrap();
+        assert_eq!(Some(""), metadata.name());
+        assert_eq!(Some(100), metadata.size());
+    }
+
+    #[test]
+    fn parse_name_with_equal_signs() {
+        let metadata =
+            parse_header_line(b"=ybegin line=128 size=100 name=weird=name.bin\r\n").unwrap();
+        assert_eq!(Some("weird=name.bin"), metadata.name());
+
+        let metadata = parse_header_line(b"=ybegin name=a=b.bin size=100\r\n").unwrap();
+        assert_eq!(Some("a=b.bin size=100"), metadata.name());
+        assert_eq!(None, metadata.size());
+    }
+
     #[test]
     fn parse_valid_header_part() {
         let parse_result = parse_header_line(b"=ypart begin=1 end=189463\n");
@@ -610,6 +2927,18 @@ mod tests {
         assert_eq!(metadata.end, Some(189_463));
     }
 
+    #[test]
+    fn parse_header_accessors() {
+        let header =
+            parse_header(b"=ybegin part=2 total=3 line=128 size=189463 name=Cat.jpg\r\n").unwrap();
+        assert_eq!(Some("Cat.jpg"), header.name());
+        assert_eq!(Some(2), header.part());
+        assert_eq!(Some(3), header.total());
+        assert_eq!(Some(128), header.line_length());
+        assert_eq!(Some(189_463), header.size());
+        assert_eq!(None, header.crc32());
+    }
+
     #[test]
     fn invalid_header_tag() {
         let parse_result = parse_header_line(b"=yparts begin=1 end=189463\n");
@@ -622,6 +2951,31 @@ mod tests {
         assert!(parse_result.is_err());
     }
 
+    #[test]
+    fn parse_ignore_unknown_keywords() {
+        let line = b"=ybegin line=128 size=100 foo=bar name=x\r\n";
+        assert_eq!(
+            Err(DecodeError::InvalidHeader {
+                line: String::from_utf8_lossy(line).to_string(),
+                position: 29,
+            }),
+            parse_header_line_with(line, false, false)
+        );
+        let metadata = parse_header_line_with(line, false, true).unwrap();
+        assert_eq!(Some(128), metadata.line_length);
+        assert_eq!(Some(100), metadata.size);
+        assert_eq!(Some("x".to_string()), metadata.name);
+
+        let metadata = parse_header_line_with(
+            b"=yend size=100 desc=a=b crc=1 crc32=ded29f4f\n",
+            true,
+            true,
+        )
+        .unwrap();
+        assert_eq!(Some(100), metadata.size);
+        assert_eq!(Some(0xded29f4f), metadata.crc32);
+    }
+
     #[test]
     fn invalid_header_invalid_begin() {
         let parse_result = parse_header_line(b"=ypart begin=a end=189463\n");
@@ -640,6 +2994,128 @@ mod tests {
         assert!(parse_result.is_err());
     }
 
+    #[test]
+    fn output_filename_strips_directories() {
+        let decode_options = DecodeOptions::new("out");
+        assert_eq!(
+            PathBuf::from("evil"),
+            decode_options
+                .output_filename(b"../../etc/cron.d/evil")
+                .unwrap()
+        );
+        assert_eq!(
+            PathBuf::from("evil.exe"),
+            decode_options.output_filename(b"..\\evil.exe").unwrap()
+        );
+        assert_eq!(
+            PathBuf::from("file.bin"),
+            decode_options.output_filename(b" file.bin ").unwrap()
+        );
+        assert!(decode_options.output_filename(b"/etc/passwd").is_err());
+        assert!(decode_options.output_filename(b"C:\\evil.exe").is_err());
+        assert!(decode_options.output_filename(b"..").is_err());
+        assert_eq!(
+            Err(DecodeError::MissingFilename),
+            decode_options.output_filename(b"")
+        );
+        assert_eq!(
+            Err(DecodeError::MissingFilename),
+            decode_options.output_filename(b" \"\" ")
+        );
+    }
+
+    #[test]
+    fn output_filename_trim_and_quotes() {
+        let decode_options = DecodeOptions::new("out");
+        assert_eq!(
+            PathBuf::from("my file.bin"),
+            decode_options
+                .output_filename(b" \"my file.bin\" ")
+                .unwrap()
+        );
+        assert_eq!(
+            PathBuf::from("\"file.bin"),
+            decode_options.output_filename(b"\"file.bin").unwrap()
+        );
+
+        let decode_options = DecodeOptions::new("out").trim_filename(false);
+        assert_eq!(
+            PathBuf::from("file.bin "),
+            decode_options.output_filename(b"file.bin ").unwrap()
+        );
+        assert_eq!(
+            PathBuf::from(" file.bin "),
+            decode_options.output_filename(b"\" file.bin \"").unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_name_bytes() {
+        let header = parse_header(b"=ybegin line=128 size=10 name=caf\xe9.txt\r\n").unwrap();
+        assert_eq!(Some(&b"caf\xe9.txt"[..]), header.name_bytes());
+        assert_eq!(Some("caf\u{fffd}.txt"), header.name());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn output_filename_raw_bytes() {
+        use std::os::unix::ffi::OsStrExt;
+        let decode_options = DecodeOptions::new("out");
+        assert_eq!(
+            std::ffi::OsStr::from_bytes(b"caf\xe9.txt"),
+            decode_options
+                .output_filename(b"dir/caf\xe9.txt")
+                .unwrap()
+                .as_os_str()
+        );
+    }
+
+    #[test]
+    fn header_line_tolerant_prefix() {
+        let line = b"\xef\xbb\xbf > >  =ybegin line=128 size=10 name=a.bin\r\n";
+        assert_eq!(None, header_line(line, false));
+        assert_eq!(
+            Some(&b"=ybegin line=128 size=10 name=a.bin\r\n"[..]),
+            header_line(line, true)
+        );
+        assert_eq!(None, header_line(b"> =yend size=10\r\n", true));
+        assert_eq!(None, header_line(b"text =ybegin line=128\r\n", true));
+    }
+
+    #[test]
+    fn output_filename_allow_subdirs() {
+        let decode_options = DecodeOptions::new("out").allow_subdirs(true);
+        assert_eq!(
+            PathBuf::from("dir/file.bin"),
+            decode_options.output_filename(b"dir/./file.bin").unwrap()
+        );
+        assert!(decode_options
+            .output_filename(b"dir/../../file.bin")
+            .is_err());
+        assert!(decode_options.output_filename(b"/dir/file.bin").is_err());
+    }
+
+    #[test]
+    fn decode_options_default_and_clone() {
+        let decode_options = DecodeOptions::default();
+        assert_eq!(PathBuf::from("."), decode_options.output_dir);
+        let template = DecodeOptions::new(PathBuf::from("out")).strict(true);
+        let decode_options = template.clone().overwrite(true);
+        assert_eq!(template.output_dir, decode_options.output_dir);
+        assert!(decode_options.strict);
+        assert!(!template.overwrite);
+    }
+
+    #[test]
+    fn roundtrip() {
+        let data = (0..1000u16).map(|c| c as u8).collect::<Vec<u8>>();
+        for line_length in [1, 2, 128, 2000] {
+            assert_eq!(Ok(()), verify_roundtrip(&data, line_length));
+        }
+        assert_eq!(Ok(()), verify_roundtrip(&[], 128));
+        assert!(verify_roundtrip(&data, 0).is_err());
+    }
+
     #[test]
     fn decode_invalid() {
         assert!(decode_buffer(b"=").unwrap().is_empty());
@@ -676,6 +3152,87 @@ mod tests {
         assert_eq!(&vec![b'.' - 0x2A], &decode_buffer(b"..").unwrap());
     }
 
+    #[test]
+    fn decode_valid_prepended_dots_second_line() {
+        assert_eq!(
+            &vec![0xff - 0x2A, b'.' - 0x2A, 0xff - 0x2A],
+            &decode_buffer(&[0xff, b'\r', b'\n', b'.', b'.', 0xff]).unwrap()
+        );
+    }
+
+    #[test]
+    fn decode_valid_dots_not_at_line_start() {
+        assert_eq!(
+            &vec![0xff - 0x2A, b'.' - 0x2A, b'.' - 0x2A],
+            &decode_buffer(&[0xff, b'.', b'.']).unwrap()
+        );
+    }
+
+    #[test]
+    fn decode_partial_trailing_escape() {
+        assert_eq!(Ok((vec![], 0)), decode_buffer_partial(b"="));
+        assert_eq!(
+            Ok((vec![0xff - 0x2A], 1)),
+            decode_buffer_partial(&[0xff, b'='])
+        );
+        assert_eq!(
+            Ok((vec![b'='.wrapping_sub(0x40 + 0x2A)], 2)),
+            decode_buffer_partial(b"==")
+        );
+        assert_eq!(
+            Ok((vec![b'='.wrapping_sub(0x40 + 0x2A)], 2)),
+            decode_buffer_partial(b"===")
+        );
+
+        let encoded = [0xff, b'=', 0x7d, 0x01];
+        let mut decoded = Vec::new();
+        let mut pending = Vec::new();
+        for chunk in encoded.chunks(2) {
+            pending.extend_from_slice(chunk);
+            let (output, consumed) = decode_buffer_partial(&pending).unwrap();
+            decoded.extend_from_slice(&output);
+            pending.drain(..consumed);
+        }
+        assert!(pending.is_empty());
+        assert_eq!(decode_buffer(&encoded).unwrap(), decoded);
+    }
+
+    #[test]
+    fn stream_decoder_any_split() {
+        let encoded = b"..a\r\n..=}\r\n\r\n.b\r\nc..\r\n=n.=";
+        let expected = decode_buffer(encoded).unwrap();
+        for chunk_size in 1..=encoded.len() {
+            let mut decoder = StreamDecoder::new();
+            let mut decoded = Vec::new();
+            for chunk in encoded.chunks(chunk_size) {
+                decoder.feed(chunk, &mut decoded).unwrap();
+            }
+            assert_eq!(expected, decoded);
+            assert_eq!(crate::crc32::crc32(&expected), decoder.finish());
+        }
+    }
+
+    #[test]
+    fn stream_decoder_dangling_escape() {
+        let mut decoder = StreamDecoder::new();
+        let mut decoded = Vec::new();
+        decoder.feed(b"ab=", &mut decoded).unwrap();
+        assert_eq!(
+            Err(DecodeError::DanglingEscape),
+            decoder.feed(b"\r\n", &mut decoded)
+        );
+
+        let mut decoder = StreamDecoder::new();
+        let mut decoded = Vec::new();
+        assert_eq!(
+            Err(DecodeError::DanglingEscape),
+            decoder.feed(b"abc=\r\n", &mut decoded)
+        );
+        assert_eq!(3, decoded.len());
+        assert_eq!(3, decoder.checksum().num_bytes());
+        assert_eq!(crate::crc32::crc32(&decoded), decoder.finish());
+    }
+
     #[test]
     fn decode_valid_prepended_single_dot() {
         assert_eq!(
@@ -1,34 +1,160 @@
-use super::constants::{CR, DEFAULT_LINE_SIZE, DOT, ESCAPE, LF, NUL};
+use super::buffer::{decode_buffer_into, encode_with, max_encoded_len, EncodeState};
+use super::constants::{DEFAULT_LINE_LENGTH, DOT, ESCAPE, ESCAPE_OFFSET, SPACE, TAB};
+use super::crc32::Crc32;
 use super::errors::EncodeError;
 
 use std::fs::File;
-use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::io::{self, BufRead, BufReader, BufWriter, Cursor, Read, Seek, SeekFrom, Write};
+use std::panic::resume_unwind;
 use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
 
 /// Options for encoding.
 /// The entry point for encoding a file (part)
 /// to a file or (TCP) stream.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct EncodeOptions {
-    line_length: u8,
+    line_length: u16,
     parts: u32,
     part: u32,
     begin: u64,
     end: u64,
+    file_crc32: Option<u32>,
+    line_ending: LineEnding,
+    verify: bool,
+    read_buffer_size: usize,
+    escape_extra: EscapeSet,
+    extra_header_fields: Vec<(String, String)>,
+}
+
+/// Description of a part encoded by `EncodeOptions::encode_file_multipart`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PartInfo {
+    /// the part number, starting at 1
+    pub part: u32,
+    /// the begin offset of the part (file offset + 1)
+    pub begin: u64,
+    /// the end offset of the part
+    pub end: u64,
+    /// the CRC32 checksum of the part
+    pub pcrc32: u32,
+}
+
+/// Summary of a part (or a complete file) encoded by `EncodeOptions`.
+///
+/// Holds the values written in the `=yend` line, and the number of bytes written to the
+/// output, so the overhead of the encoding is `encoded_bytes - bytes`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EncodeSummary {
+    /// the number of (unencoded) bytes in the part
+    pub bytes: u64,
+    /// the CRC32 checksum of the part
+    pub crc32: u32,
+    /// the part number, 1 for single part encoding
+    pub part: u32,
+    /// the number of bytes written to the output, including the header and footer lines
+    pub encoded_bytes: u64,
+}
+
+/// The line separator used in the encoded output.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LineEnding {
+    /// Line Feed only (`"\n"`).
+    Lf,
+    /// Carriage Return + Line Feed (`"\r\n"`), as required by the yEnc specification (default).
+    #[default]
+    CrLf,
+}
+
+impl LineEnding {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+}
+
+/// Characters that are escaped in addition to the critical characters (NUL, LF, CR and `=`).
+///
+/// The default set is empty. A character in the set is escaped wherever it occurs, not only
+/// at the start or end of a line.
+///
+/// # Example
+/// ```rust
+/// let escape = yenc::EscapeSet::none().tab(true).space(true);
+/// let encode_options = yenc::EncodeOptions::new().escape_extra(escape);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EscapeSet {
+    tab: bool,
+    space: bool,
+    dot: bool,
+}
+
+impl EscapeSet {
+    /// The empty set: only the critical characters are escaped.
+    pub const fn none() -> EscapeSet {
+        EscapeSet {
+            tab: false,
+            space: false,
+            dot: false,
+        }
+    }
+
+    /// Escapes TAB characters.
+    pub const fn tab(mut self, tab: bool) -> EscapeSet {
+        self.tab = tab;
+        self
+    }
+
+    /// Escapes SPACE characters.
+    pub const fn space(mut self, space: bool) -> EscapeSet {
+        self.space = space;
+        self
+    }
+
+    /// Escapes dots, instead of doubling a dot at the start of a line.
+    pub const fn dot(mut self, dot: bool) -> EscapeSet {
+        self.dot = dot;
+        self
+    }
+
+    /// Returns whether the encoded byte is in the set.
+    #[inline(always)]
+    fn contains(self, encoded_byte: u8) -> bool {
+        match encoded_byte {
+            TAB => self.tab,
+            SPACE => self.space,
+            DOT => self.dot,
+            _ => false,
+        }
+    }
 }
 
 impl Default for EncodeOptions {
     /// Constructs a new EncodeOptions instance, with the following defaults:
     /// line_length = 128.
     /// parts = 1,
-    /// part = begin = end = 0
+    /// part = begin = end = 0,
+    /// file_crc32 = None,
+    /// line_ending = CrLf,
+    /// escape_extra = EscapeSet::none(),
+    /// no extra header fields
     fn default() -> Self {
         EncodeOptions {
-            line_length: DEFAULT_LINE_SIZE,
+            line_length: DEFAULT_LINE_LENGTH,
             parts: 1,
             part: 0,
             begin: 0,
             end: 0,
+            file_crc32: None,
+            line_ending: LineEnding::CrLf,
+            verify: false,
+            read_buffer_size: 8192,
+            escape_extra: EscapeSet::none(),
+            extra_header_fields: Vec::new(),
         }
     }
 }
@@ -39,8 +165,8 @@ impl EncodeOptions {
         Default::default()
     }
 
-    /// Sets the maximum line length.
-    pub fn line_length(mut self, line_length: u8) -> EncodeOptions {
+    /// Sets the maximum line length (default=128).
+    pub fn line_length(mut self, line_length: u16) -> EncodeOptions {
         self.line_length = line_length;
         self
     }
@@ -62,7 +188,7 @@ impl EncodeOptions {
     }
 
     /// Sets the begin (which is the file offset + 1).
-    /// Only used when `parts > 1`.
+    /// When `parts == 1` and no begin is specified, encoding starts at the beginning (1).
     /// The size of the part is `end - begin + 1`.
     pub fn begin(mut self, begin: u64) -> EncodeOptions {
         self.begin = begin;
@@ -70,7 +196,7 @@ impl EncodeOptions {
     }
 
     /// Sets the end.
-    /// Only used when `parts > 1`.
+    /// When `parts == 1` and no end is specified, encoding stops at the end of the input.
     /// The size of the part is `end - begin + 1`.
     /// `end` should be larger than `begin`, otherwise an overflow error occurrs.
     pub fn end(mut self, end: u64) -> EncodeOptions {
@@ -78,6 +204,71 @@ impl EncodeOptions {
         self
     }
 
+    /// Sets the line separator (default=`LineEnding::CrLf`).
+    /// Applies to the wrapped lines as well as to the header and footer lines.
+    pub fn line_ending(mut self, line_ending: LineEnding) -> EncodeOptions {
+        self.line_ending = line_ending;
+        self
+    }
+
+    /// Sets the CRC32 checksum of the complete file.
+    /// Only used when `parts > 1`: the checksum is written as `crc32=` in the
+    /// `=yend` line of the last part, so the reassembled file can be verified.
+    pub fn file_crc32(mut self, file_crc32: u32) -> EncodeOptions {
+        self.file_crc32 = Some(file_crc32);
+        self
+    }
+
+    /// Verifies the encoded data while encoding (default=false).
+    ///
+    /// When set, every encoded chunk is decoded again and compared with the input data, before
+    /// it is written. This is slower, but guards against errors in the encoder.
+    pub fn verify(mut self, verify: bool) -> EncodeOptions {
+        self.verify = verify;
+        self
+    }
+
+    /// Sets the number of bytes read from the input at once (default=8192).
+    ///
+    /// A larger buffer reduces the number of reads and encode calls for large inputs.
+    /// Must be larger than 0.
+    pub fn read_buffer_size(mut self, read_buffer_size: usize) -> EncodeOptions {
+        self.read_buffer_size = read_buffer_size;
+        self
+    }
+
+    /// Sets the characters that are escaped in addition to the critical characters
+    /// (default=`EscapeSet::none()`).
+    ///
+    /// Escaping TAB and SPACE everywhere gives the most compatible output, for transports
+    /// that trim or otherwise alter whitespace, at the cost of a slightly larger output.
+    pub fn escape_extra(mut self, escape_extra: EscapeSet) -> EncodeOptions {
+        self.escape_extra = escape_extra;
+        self
+    }
+
+    /// Appends a `key=value` field to the `=ybegin` line, after the standard fields and
+    /// before `name`, which is always the last field. Fields are written in the order in which
+    /// they are added.
+    ///
+    /// The key must consist of lowercase ASCII letters and digits, and must not be one of the
+    /// standard keywords (`begin`, `crc32`, `end`, `line`, `name`, `part`, `pcrc32`, `size` and
+    /// `total`). The value must not contain a space, CR or LF, so decoders can tell where it
+    /// ends, see `DecodeOptions::ignore_unknown_keywords`. Invalid fields are reported by
+    /// `check_options` as `EncodeError::InvalidHeaderField`.
+    ///
+    /// # Example
+    /// ```rust
+    /// let encode_options = yenc::EncodeOptions::new().extra_header_field("desc", "holiday_photos");
+    /// let output = encode_options.encode_bytes(b"yEnc", "a.bin").unwrap();
+    /// assert!(output.starts_with(b"=ybegin line=128 size=4 desc=holiday_photos name=a.bin\r\n"));
+    /// ```
+    pub fn extra_header_field(mut self, key: &str, value: &str) -> EncodeOptions {
+        self.extra_header_fields
+            .push((key.to_string(), value.to_string()));
+        self
+    }
+
     /// Encodes the input file and writes it to the writer. For multi-part encoding, only
     /// one part is encoded. In case of multipart, the part number, begin and end offset need
     /// to be specified in the `EncodeOptions`. When directly encoding to an NNTP stream, the
@@ -96,7 +287,7 @@ impl EncodeOptions {
     /// # Errors
     /// - when the output file already exists
     ///
-    pub fn encode_file<P, W>(&self, input_path: P, output: W) -> Result<(), EncodeError>
+    pub fn encode_file<P, W>(&self, input_path: P, output: W) -> Result<EncodeSummary, EncodeError>
     where
         P: AsRef<Path>,
         W: Write,
@@ -118,7 +309,16 @@ impl EncodeOptions {
     /// - EncodeError::PartBeginOffsetMissing
     /// - EncodeError::PartEndOffsetMissing
     /// - EncodeError::PartOffsetsInvalidRange
+    /// - EncodeError::InvalidLineLength
+    /// - EncodeError::InvalidReadBufferSize
+    /// - EncodeError::InvalidHeaderField
     pub fn check_options(&self) -> Result<(), EncodeError> {
+        if self.line_length == 0 {
+            return Err(EncodeError::InvalidLineLength);
+        }
+        if self.read_buffer_size == 0 {
+            return Err(EncodeError::InvalidReadBufferSize);
+        }
         if self.parts > 1 && self.part == 0 {
             return Err(EncodeError::PartNumberMissing);
         }
@@ -131,199 +331,842 @@ impl EncodeOptions {
         if self.parts > 1 && self.begin > self.end {
             return Err(EncodeError::PartOffsetsInvalidRange);
         }
+        for (key, value) in &self.extra_header_fields {
+            if !is_valid_header_field(key, value) {
+                return Err(EncodeError::InvalidHeaderField { key: key.clone() });
+            }
+        }
         Ok(())
     }
 
+    /// Returns the begin and end offset of the data to encode.
+    /// For single part encoding, begin defaults to 1 and end defaults to the length.
+    /// The range of empty data ends just before its begin offset.
+    ///
+    /// # Errors
+    /// - `EncodeError::PartOffsetsInvalidRange` when the begin offset is after the end offset
+    fn range(&self, length: u64) -> Result<(u64, u64), EncodeError> {
+        let (begin, end) = if self.parts == 1 {
+            let begin = self.begin.max(1);
+            let end = if self.end == 0 { length } else { self.end };
+            (begin, end)
+        } else {
+            (self.begin, self.end)
+        };
+        if begin > end.saturating_add(1) {
+            return Err(EncodeError::PartOffsetsInvalidRange);
+        }
+        Ok((begin, end))
+    }
+
     /// Encodes the date from input from stream and writes the encoded data to the output stream.
     /// The input stream does not need to be a file, therefore, size and input_filename
     /// must be specified. The input_filename ends up as the filename in the yenc header.
-    #[allow(clippy::write_with_newline)]
+    ///
+    /// Returns the number of bytes and the CRC32 checksum written in the `=yend` line.
     pub fn encode_stream<R, W>(
         &self,
         input: R,
         output: W,
         length: u64,
         input_filename: &str,
-    ) -> Result<(), EncodeError>
+    ) -> Result<EncodeSummary, EncodeError>
     where
         R: Read + Seek,
         W: Write,
+    {
+        self.encode_part(input, output, length, input_filename)
+    }
+
+    /// Encodes the data and returns the complete encoded article: the `=ybegin` line (and
+    /// `=ypart` line for multipart), the encoded lines and the `=yend` line.
+    ///
+    /// For multipart encoding, only the `begin..=end` range of `data` is encoded, and the size
+    /// in the `=ybegin` line is the length of `data`.
+    ///
+    /// # Example
+    /// ```rust
+    /// let encoded = yenc::EncodeOptions::new().encode_bytes(b"yEnc", "a.txt").unwrap();
+    /// assert!(encoded.starts_with(b"=ybegin line=128 size=4 name=a.txt\r\n\xa3o\x98\x8d\r\n"));
+    /// assert!(encoded.ends_with(b"=yend size=4 crc32=6ee709ea\r\n"));
+    /// ```
+    /// # Errors
+    /// - when the options are invalid, see `check_options`
+    /// - `EncodeError::PartOffsetsInvalidRange` when the begin offset is after the end offset,
+    ///   which defaults to the length of `data` for a single part
+    /// - when the `begin..=end` range is not within `data`
+    pub fn encode_bytes(&self, data: &[u8], name: &str) -> Result<Vec<u8>, EncodeError> {
+        let mut output = Vec::with_capacity(max_encoded_len(data.len(), self.line_length) + 128);
+        self.encode_stream(Cursor::new(data), &mut output, data.len() as u64, name)?;
+        Ok(output)
+    }
+
+    /// Encodes the data from the input stream from its current position, without seeking, and
+    /// writes the encoded data to the output stream.
+    ///
+    /// Same as `encode_stream`, but the input only needs to implement `Read`, so data can be
+    /// encoded directly from a pipe or socket. For a single part, `length` bytes are encoded;
+    /// for multiple parts, the input must be positioned at the begin offset of the part, and
+    /// `end - begin + 1` bytes are encoded.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// let encode_options = yenc::EncodeOptions::new();
+    /// let length = 1000;
+    /// encode_options
+    ///     .encode_stream_sequential(std::io::stdin(), std::io::stdout(), length, "stdin.bin")
+    ///     .unwrap();
+    /// ```
+    /// # Errors
+    /// - when the input ends before all bytes are read
+    /// - when the options are invalid, see `check_options`
+    pub fn encode_stream_sequential<R, W>(
+        &self,
+        input: R,
+        output: W,
+        length: u64,
+        input_filename: &str,
+    ) -> Result<EncodeSummary, EncodeError>
+    where
+        R: Read,
+        W: Write,
+    {
+        self.encode_part_sequential(input, output, Some(length), input_filename)
+    }
+
+    /// Encodes the data from the input stream up to its end, when its length is not known in
+    /// advance, and writes the encoded data to the output stream.
+    ///
+    /// The `size` keyword is omitted from the `=ybegin` line. The `=yend` line contains the
+    /// actual number of encoded bytes and the CRC32 checksum. Only single part encoding is
+    /// supported: the `parts`, `part`, `begin` and `end` options must not be set.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// let encode_options = yenc::EncodeOptions::new();
+    /// encode_options
+    ///     .encode_stream_unknown_size(std::io::stdin(), std::io::stdout(), "stdin.bin")
+    ///     .unwrap();
+    /// ```
+    /// # Errors
+    /// - `EncodeError::InvalidPartCount` when more than one part is specified
+    /// - when an I/O error occurs
+    pub fn encode_stream_unknown_size<R, W>(
+        &self,
+        input: R,
+        output: W,
+        input_filename: &str,
+    ) -> Result<EncodeSummary, EncodeError>
+    where
+        R: Read,
+        W: Write,
+    {
+        if self.parts != 1 {
+            return Err(EncodeError::InvalidPartCount);
+        }
+        let options = EncodeOptions {
+            begin: 0,
+            end: 0,
+            ..self.clone()
+        };
+        options.encode_part_sequential(input, output, None, input_filename)
+    }
+
+    /// Encodes the part (or the complete input in case of single part) to the output stream.
+    /// Returns the summary of the encoded part.
+    fn encode_part<R, W>(
+        &self,
+        input: R,
+        output: W,
+        length: u64,
+        input_filename: &str,
+    ) -> Result<EncodeSummary, EncodeError>
+    where
+        R: Read + Seek,
+        W: Write,
+    {
+        self.check_options()?;
+        let mut input = input;
+        let (begin, _) = self.range(length)?;
+        input.seek(SeekFrom::Start(begin - 1))?;
+        self.encode_part_sequential(input, output, Some(length), input_filename)
+    }
+
+    /// Encodes the part from the current position of the input stream to the output stream.
+    /// Returns the summary of the encoded part.
+    /// When `length` is `None`, the `size` keyword is omitted from the `=ybegin` line, and a
+    /// single part is encoded up to the end of the input.
+    fn encode_part_sequential<R, W>(
+        &self,
+        input: R,
+        output: W,
+        length: Option<u64>,
+        input_filename: &str,
+    ) -> Result<EncodeSummary, EncodeError>
+    where
+        R: Read,
+        W: Write,
     {
         let mut rdr = BufReader::new(input);
-        let mut checksum = crc32fast::Hasher::new();
-        let mut buffer = [0u8; 8192];
+        let mut checksum = Crc32::new();
+        self.check_options()?;
+
+        let mut buffer = vec![0u8; self.read_buffer_size];
         let mut col = 0;
-        let mut num_bytes = 0;
         let mut output = BufWriter::new(output);
+        let mut encoded = Vec::with_capacity(max_encoded_len(buffer.len(), self.line_length));
+        let eol = self.line_ending.as_str();
+        let mut verify_buffers = (Vec::new(), Vec::new());
 
-        self.check_options()?;
+        let mut header = format_ybegin_with(
+            self.part,
+            self.parts,
+            self.line_length,
+            length,
+            &self.extra_header_fields,
+            input_filename,
+        );
+        header.push_str(eol);
+        if self.parts > 1 {
+            header.push_str(&format!(
+                "=ypart begin={} end={}{}",
+                self.begin, self.end, eol
+            ));
+        }
+        output.write_all(header.as_bytes())?;
+        let mut encoded_bytes = header.len() as u64;
 
-        if self.parts == 1 {
-            write!(
-                output,
-                "=ybegin line={} size={} name={}\r\n",
-                self.line_length, length, input_filename
-            )?;
-        } else {
-            write!(
-                output,
-                "=ybegin part={} line={} size={} name={}\r\n",
-                self.part, self.line_length, length, input_filename
-            )?;
+        // the number of bytes left to encode, or None to encode up to the end of the input
+        let mut remainder = match length {
+            None if self.parts == 1 && self.end == 0 => None,
+            _ => {
+                let (begin, end) = self.range(length.unwrap_or(0))?;
+                Some((end - (begin - 1)) as usize)
+            }
+        };
+        loop {
+            let buf_slice = match remainder {
+                Some(0) => break,
+                Some(remainder) if remainder < buffer.len() => &mut buffer[0..remainder],
+                _ => &mut buffer[..],
+            };
+            let buf_slice = match remainder {
+                Some(_) => {
+                    rdr.read_exact(buf_slice)?;
+                    buf_slice
+                }
+                None => match rdr.read(buf_slice)? {
+                    0 => break,
+                    length => &mut buf_slice[..length],
+                },
+            };
+            // the last byte of the data ends the last line, so whitespace in it is escaped
+            let end_of_data = match remainder {
+                Some(remainder) => remainder == buf_slice.len(),
+                None => rdr.fill_buf()?.is_empty(),
+            };
+            let offset = checksum.num_bytes() as u64;
+            checksum.update_with_slice(buf_slice);
+            encoded.clear();
+            let line_start = col == 0;
+            col = encode_into(
+                buf_slice,
+                col,
+                self.line_length,
+                self.line_ending,
+                self.escape_extra,
+                end_of_data,
+                &mut encoded,
+            );
+            if self.verify {
+                verify_chunk(buf_slice, &encoded, line_start, offset, &mut verify_buffers)?;
+            }
+            output.write_all(&encoded)?;
+            encoded_bytes += encoded.len() as u64;
+            if let Some(ref mut remainder) = remainder {
+                *remainder -= buf_slice.len();
+            }
         }
 
-        if self.parts > 1 {
-            write!(output, "=ypart begin={} end={}\r\n", self.begin, self.end)?;
+        let mut summary = EncodeSummary {
+            bytes: checksum.num_bytes() as u64,
+            crc32: checksum.crc(),
+            part: self.part.max(1),
+            encoded_bytes: 0,
+        };
+        let mut footer = format!("{}{}", eol, format_yend(&summary, self.parts));
+        match self.file_crc32 {
+            Some(file_crc32) if self.parts > 1 && self.part == self.parts => {
+                footer.push_str(&format!(" crc32={:08x}", file_crc32))
+            }
+            _ => {}
         }
+        footer.push_str(eol);
+        output.write_all(footer.as_bytes())?;
+        summary.encoded_bytes = encoded_bytes + footer.len() as u64;
+        Ok(summary)
+    }
 
-        rdr.seek(SeekFrom::Start(self.begin - 1))?;
+    /// Encodes the input file in `parts` parts, and writes each part to the writer returned by
+    /// `sink` for that part number.
+    ///
+    /// The begin and end offsets of the parts are computed from the file size. All parts
+    /// have the same size, except for the last part, which also contains the remainder.
+    /// The other options (line length, line ending) are taken from `self`; the `parts`, `part`,
+    /// `begin` and `end` options are ignored.
+    ///
+    /// Returns a description of every encoded part.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// let encode_options = yenc::EncodeOptions::new();
+    /// let parts = encode_options
+    ///     .encode_file_multipart("test1.bin", 3, |part| {
+    ///         std::fs::File::create(format!("test1.bin.{:03}", part))
+    ///     })
+    ///     .unwrap();
+    /// ```
+    /// # Errors
+    /// - `EncodeError::InvalidPartCount` when `parts` is 0 or larger than the file size
+    /// - when the sink returns an I/O error
+    pub fn encode_file_multipart<P, F, W>(
+        &self,
+        input_path: P,
+        parts: u32,
+        mut sink: F,
+    ) -> Result<Vec<PartInfo>, EncodeError>
+    where
+        P: AsRef<Path>,
+        F: FnMut(u32) -> io::Result<W>,
+        W: Write,
+    {
+        let input_filename = input_path.as_ref().file_name();
+        let input_filename = match input_filename {
+            Some(s) => s.to_str().unwrap_or(""),
+            None => "",
+        };
+        let mut input_file = File::open(&input_path)?;
+        let length = input_file.metadata()?.len();
 
-        let mut remainder = (self.end - self.begin + 1) as usize;
-        while remainder > 0 {
-            let buf_slice = if remainder > buffer.len() {
-                &mut buffer[..]
-            } else {
-                &mut buffer[0..remainder]
-            };
-            rdr.read_exact(buf_slice)?;
-            checksum.update(buf_slice);
-            num_bytes += buf_slice.len();
-            col = encode_buffer(buf_slice, col, self.line_length, &mut output)?;
-            remainder -= buf_slice.len();
+        let plan = plan_parts(length, parts);
+        if plan.is_empty() {
+            return Err(EncodeError::InvalidPartCount);
         }
 
-        if self.parts > 1 {
-            write!(
-                output,
-                "\r\n=yend size={} part={} pcrc32={:08x}\r\n",
-                num_bytes,
-                self.part,
-                checksum.finalize()
-            )?;
-        } else {
-            write!(
-                output,
-                "\r\n=yend size={} crc32={:08x}\r\n",
-                num_bytes,
-                checksum.finalize()
-            )?;
+        let mut part_infos = Vec::with_capacity(plan.len());
+        for (part, begin, end) in plan {
+            let options = self.clone().parts(parts).part(part).begin(begin).end(end);
+            let output = sink(part)?;
+            let pcrc32 = options
+                .encode_part(&mut input_file, output, length, input_filename)?
+                .crc32;
+            part_infos.push(PartInfo {
+                part,
+                begin,
+                end,
+                pcrc32,
+            });
         }
-        Ok(())
+        Ok(part_infos)
     }
-}
 
-/// Encodes the input buffer and writes it to the writer.
-///
-/// Lines are wrapped with a maximum of `line_length` characters per line.
-/// Does not include the header and footer lines.
-/// Only `encode_stream` and `encode_file` produce the headers in the output.
-/// The `col` parameter is the starting offset in the row. The result contains the new offset.
-pub fn encode_buffer<W>(
-    input: &[u8],
-    col: u8,
-    line_length: u8,
-    writer: W,
-) -> Result<u8, EncodeError>
-where
-    W: Write,
-{
-    let mut col = col;
-    let mut writer = writer;
-    let mut v = Vec::<u8>::with_capacity(((input.len() as f64) * 1.04) as usize);
-    input.iter().for_each(|&b| {
-        let encoded = encode_byte(b);
-        v.push(encoded.0);
-        col += match encoded.0 {
-            ESCAPE => {
-                v.push(encoded.1);
-                2
-            }
-            DOT if col == 0 => {
-                v.push(DOT);
-                2
+    /// Encodes the input file in `parts` parts concurrently, and writes each part to the
+    /// writer returned by `sink` for that part number.
+    ///
+    /// Same as `encode_file_multipart`, but the parts are encoded by a number of threads, at
+    /// most the available parallelism. Every thread opens its own handle to the input file,
+    /// and seeks to the range of the part it encodes, so no state is shared except `sink`.
+    ///
+    /// The parts are started in order, but may finish in any order, so `sink` can be called
+    /// from several threads at once, and the writers are written to concurrently. On a
+    /// single disk, the concurrent reads and writes can contend, which limits the speedup.
+    /// The returned descriptions are ordered by part number.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// let encode_options = yenc::EncodeOptions::new();
+    /// let parts = encode_options
+    ///     .encode_file_parallel("test1.bin", 30, |part| {
+    ///         std::fs::File::create(format!("test1.bin.{:03}", part))
+    ///     })
+    ///     .unwrap();
+    /// ```
+    /// # Errors
+    /// - `EncodeError::InvalidPartCount` when `parts` is 0 or larger than the file size
+    /// - when the sink returns an I/O error; parts that were not started yet are skipped
+    pub fn encode_file_parallel<P, F, W>(
+        &self,
+        input_path: P,
+        parts: u32,
+        sink: F,
+    ) -> Result<Vec<PartInfo>, EncodeError>
+    where
+        P: AsRef<Path>,
+        F: Fn(u32) -> io::Result<W> + Sync,
+        W: Write,
+    {
+        let input_path = input_path.as_ref();
+        let input_filename = match input_path.file_name() {
+            Some(s) => s.to_str().unwrap_or(""),
+            None => "",
+        };
+        let length = File::open(input_path)?.metadata()?.len();
+
+        let plan = plan_parts(length, parts);
+        if plan.is_empty() {
+            return Err(EncodeError::InvalidPartCount);
+        }
+
+        let threads = thread::available_parallelism()
+            .map_or(1, |threads| threads.get())
+            .min(plan.len());
+        // the index in plan of the next part to encode
+        let next = AtomicUsize::new(0);
+        let encode_parts = || -> Result<Vec<PartInfo>, EncodeError> {
+            let mut input_file = File::open(input_path)?;
+            let mut part_infos = Vec::new();
+            while let Some(&(part, begin, end)) = plan.get(next.fetch_add(1, Ordering::Relaxed)) {
+                let options = self.clone().parts(parts).part(part).begin(begin).end(end);
+                let result = sink(part).map_err(EncodeError::from).and_then(|output| {
+                    options.encode_part(&mut input_file, output, length, input_filename)
+                });
+                match result {
+                    Ok(summary) => part_infos.push(PartInfo {
+                        part,
+                        begin,
+                        end,
+                        pcrc32: summary.crc32,
+                    }),
+                    Err(err) => {
+                        next.store(plan.len(), Ordering::Relaxed);
+                        return Err(err);
+                    }
+                }
             }
-            _ => 1,
+            Ok(part_infos)
         };
-        if col >= line_length {
-            v.push(CR);
-            v.push(LF);
-            col = 0;
+
+        let results = thread::scope(|scope| {
+            let handles = (0..threads)
+                .map(|_| scope.spawn(encode_parts))
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap_or_else(|panic| resume_unwind(panic)))
+                .collect::<Vec<_>>()
+        });
+        let mut part_infos = Vec::with_capacity(plan.len());
+        for result in results {
+            part_infos.extend(result?);
         }
-    });
-    writer.write_all(&v)?;
-    Ok(col)
+        part_infos.sort_unstable_by_key(|part_info| part_info.part);
+        Ok(part_infos)
+    }
 }
 
-#[inline(always)]
-fn encode_byte(input_byte: u8) -> (u8, u8) {
-    let mut output = (0, 0);
+/// Decodes the encoded chunk and compares it with the input chunk.
+///
+/// When the chunk does not start at the beginning of a line, a placeholder byte is decoded
+/// first, so a leading dot is not taken for a doubled dot at the start of a line.
+fn verify_chunk(
+    input: &[u8],
+    encoded: &[u8],
+    line_start: bool,
+    offset: u64,
+    buffers: &mut (Vec<u8>, Vec<u8>),
+) -> Result<(), EncodeError> {
+    let (prefixed, decoded) = buffers;
+    let skip = if line_start { 0 } else { 1 };
+    prefixed.clear();
+    if !line_start {
+        prefixed.push(b'*');
+    }
+    prefixed.extend_from_slice(encoded);
+    decoded.resize(prefixed.len(), 0);
+    let length = decode_buffer_into(prefixed, decoded).unwrap_or(0);
+    let decoded = decoded.get(skip..length).unwrap_or(&[]);
+    if decoded != input {
+        let position = decoded
+            .iter()
+            .zip(input)
+            .position(|(a, b)| a != b)
+            .unwrap_or_else(|| decoded.len().min(input.len()));
+        return Err(EncodeError::VerificationFailed {
+            offset: offset + position as u64,
+        });
+    }
+    Ok(())
+}
 
-    let output_byte = input_byte.overflowing_add(42).0;
-    match output_byte {
-        LF | CR | NUL | ESCAPE => {
-            output.0 = ESCAPE;
-            output.1 = output_byte.overflowing_add(64).0;
-        }
-        _ => {
-            output.0 = output_byte;
-        }
+/// Formats the `=ybegin` line, without the line ending.
+///
+/// `part` is only used when `parts > 1`. When `size` is `None`, the `size` keyword is omitted.
+/// Can be used to compose an article, with `format_yend` and `encode_buffer`.
+///
+/// # Example
+/// ```rust
+/// assert_eq!(
+///     "=ybegin part=2 total=3 line=128 size=1000 name=a.bin",
+///     yenc::format_ybegin(2, 3, 128, Some(1000), "a.bin")
+/// );
+/// ```
+pub fn format_ybegin(
+    part: u32,
+    parts: u32,
+    line_length: u16,
+    size: Option<u64>,
+    name: &str,
+) -> String {
+    format_ybegin_with(part, parts, line_length, size, &[], name)
+}
+
+/// Formats the `=ybegin` line like `format_ybegin`, with the extra fields before `name`.
+fn format_ybegin_with(
+    part: u32,
+    parts: u32,
+    line_length: u16,
+    size: Option<u64>,
+    extra_fields: &[(String, String)],
+    name: &str,
+) -> String {
+    let mut line = if parts > 1 {
+        format!("=ybegin part={} total={} line={}", part, parts, line_length)
+    } else {
+        format!("=ybegin line={}", line_length)
     };
-    output
+    if let Some(size) = size {
+        line.push_str(&format!(" size={}", size));
+    }
+    for (key, value) in extra_fields {
+        line.push_str(&format!(" {}={}", key, value));
+    }
+    line.push_str(&format!(" name={}", name));
+    line
 }
 
-#[cfg(test)]
-mod tests {
-    use super::super::constants::{CR, ESCAPE, LF, NUL};
-    use super::{encode_buffer, encode_byte, EncodeOptions};
+/// Returns whether the key and value can be written as an extra field in the `=ybegin` line.
+fn is_valid_header_field(key: &str, value: &str) -> bool {
+    !key.is_empty()
+        && key
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit())
+        && !matches!(
+            key,
+            "begin" | "crc32" | "end" | "line" | "name" | "part" | "pcrc32" | "size" | "total"
+        )
+        && !value.contains([' ', '\r', '\n'])
+}
 
-    #[test]
-    fn escape_null() {
-        assert_eq!((ESCAPE, 0x40), encode_byte(214));
+/// Formats the `=yend` line for an encoded part, without the line ending.
+///
+/// For a single part (`parts == 1`), the line contains the size and the `crc32` of the file;
+/// for multiple parts, the size, part number and `pcrc32` of the part.
+///
+/// # Example
+/// ```rust
+/// let summary = yenc::EncodeSummary { bytes: 4, crc32: 0x6ee709ea, part: 1, encoded_bytes: 0 };
+/// assert_eq!("=yend size=4 crc32=6ee709ea", yenc::format_yend(&summary, 1));
+/// ```
+pub fn format_yend(summary: &EncodeSummary, parts: u32) -> String {
+    if parts > 1 {
+        format!(
+            "=yend size={} part={} pcrc32={:08x}",
+            summary.bytes, summary.part, summary.crc32
+        )
+    } else {
+        format!("=yend size={} crc32={:08x}", summary.bytes, summary.crc32)
     }
+}
 
-    /*
-    #[test]
-    fn escape_tab() {
-        let mut output = [0u8; 2];
-        assert_eq!(2, encode_byte(214 + TAB, &mut output));
-        assert_eq!(vec![ESCAPE, 0x49], output);
+/// Splits a file of `total_size` bytes in `parts` parts.
+///
+/// Returns the `(part, begin, end)` of every part, with 1-based, inclusive offsets, as used by
+/// `EncodeOptions::part`, `begin` and `end`. All parts have the same size, except for the last
+/// part, which also contains the remainder. Returns an empty vector when `parts` is 0 or larger
+/// than `total_size`.
+///
+/// # Example
+/// ```rust
+/// assert_eq!(vec![(1, 1, 3), (2, 4, 6), (3, 7, 10)], yenc::plan_parts(10, 3));
+/// ```
+pub fn plan_parts(total_size: u64, parts: u32) -> Vec<(u32, u64, u64)> {
+    if parts == 0 || u64::from(parts) > total_size.max(1) {
+        return Vec::new();
     }
-    */
+    let part_size = total_size / u64::from(parts);
+    (1..=parts)
+        .map(|part| {
+            let begin = u64::from(part - 1) * part_size + 1;
+            let end = if part == parts {
+                total_size
+            } else {
+                u64::from(part) * part_size
+            };
+            (part, begin, end)
+        })
+        .collect()
+}
 
-    #[test]
-    fn escape_lf() {
-        assert_eq!((ESCAPE, 0x4A), encode_byte(214 + LF));
+/// Splits a file of `total_size` bytes in parts of `max_part_bytes` bytes.
+///
+/// Returns the `(part, begin, end)` of every part, like `plan_parts`. All parts contain
+/// `max_part_bytes` bytes, except for the last part, which contains the remainder. Returns an
+/// empty vector when `max_part_bytes` is 0, or when more than `u32::MAX` parts are needed.
+///
+/// # Example
+/// ```rust
+/// assert_eq!(vec![(1, 1, 4), (2, 5, 8), (3, 9, 10)], yenc::plan_parts_by_size(10, 4));
+/// ```
+pub fn plan_parts_by_size(total_size: u64, max_part_bytes: u64) -> Vec<(u32, u64, u64)> {
+    if max_part_bytes == 0 {
+        return Vec::new();
     }
+    let parts = total_size / max_part_bytes + u64::from(total_size % max_part_bytes != 0);
+    let parts = match u32::try_from(parts.max(1)) {
+        Ok(parts) => parts,
+        Err(_) => return Vec::new(),
+    };
+    (1..=parts)
+        .map(|part| {
+            let begin = u64::from(part - 1) * max_part_bytes + 1;
+            let end = begin.saturating_add(max_part_bytes - 1).min(total_size);
+            (part, begin, end)
+        })
+        .collect()
+}
 
-    #[test]
-    fn escape_cr() {
-        assert_eq!((ESCAPE, 0x4D), encode_byte(214 + CR));
+/// A writer that yEnc-encodes all data written to it, and writes the encoded data to the
+/// underlying writer.
+///
+/// Lines are wrapped at the line length across `write` calls. The header line is not written;
+/// the footer line can be written with `finish_with_footer`.
+///
+/// # Example
+/// ```rust
+/// use std::io::Write;
+/// let mut writer = yenc::EncodeWriter::new(Vec::new(), 128).unwrap();
+/// writer.write_all(b"yEnc").unwrap();
+/// let encoded = writer.finish().unwrap();
+/// assert_eq!(b"\xa3o\x98\x8d\r\n", encoded.as_slice());
+/// ```
+#[derive(Debug)]
+pub struct EncodeWriter<W: Write> {
+    writer: W,
+    line_length: u16,
+    col: u16,
+    checksum: Crc32,
+    encoded: Vec<u8>,
+    // a SPACE or TAB at the end of the data written so far, which is escaped when it turns out
+    // to end the data
+    pending_whitespace: Option<u8>,
+}
+
+impl<W> EncodeWriter<W>
+where
+    W: Write,
+{
+    /// Constructs a new EncodeWriter, that writes lines of at most `line_length` characters.
+    ///
+    /// # Errors
+    /// - `EncodeError::InvalidLineLength` when `line_length` is 0, like
+    ///   `EncodeOptions::check_options`
+    pub fn new(writer: W, line_length: u16) -> Result<EncodeWriter<W>, EncodeError> {
+        EncodeOptions::new()
+            .line_length(line_length)
+            .check_options()?;
+        Ok(EncodeWriter {
+            writer,
+            line_length,
+            col: 0,
+            checksum: Crc32::new(),
+            encoded: Vec::new(),
+            pending_whitespace: None,
+        })
     }
 
-    /*
-    #[test]
-    fn escape_space() {
-        let mut output = [0u8; 2];
-        assert_eq!(2, encode_byte(214 + SPACE, &mut output));
-        assert_eq!(vec![ESCAPE, 0x60], output);
+    /// The checksum and the number of bytes of the data written so far.
+    pub fn checksum(&self) -> &Crc32 {
+        &self.checksum
     }
-    */
 
-    #[test]
-    fn escape_equal_sign() {
-        assert_eq!((ESCAPE, 0x7D), encode_byte(ESCAPE - 42));
+    /// Ends the last line, flushes, and returns the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.end_line()?;
+        self.writer.flush()?;
+        Ok(self.writer)
     }
 
-    #[test]
-    fn non_escaped() {
-        for x in 0..256u16 {
-            let encoded = (x as u8).overflowing_add(42).0;
-            if encoded != NUL && encoded != CR && encoded != LF && encoded != ESCAPE {
-                assert_eq!((encoded, 0), encode_byte(x as u8));
-            }
+    /// Ends the last line, writes the `=yend` line with the size and CRC32 checksum of the
+    /// data written, flushes, and returns the underlying writer.
+    pub fn finish_with_footer(mut self) -> io::Result<W> {
+        self.end_line()?;
+        let summary = EncodeSummary {
+            bytes: self.checksum.num_bytes() as u64,
+            crc32: self.checksum.crc(),
+            part: 1,
+            encoded_bytes: 0,
+        };
+        write!(self.writer, "{}\r\n", format_yend(&summary, 1))?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn end_line(&mut self) -> io::Result<()> {
+        if let Some(whitespace) = self.pending_whitespace.take() {
+            self.writer
+                .write_all(&[ESCAPE, whitespace.wrapping_add(ESCAPE_OFFSET)])?;
+        }
+        if self.col > 0 {
+            self.writer.write_all(b"\r\n")?;
+            self.col = 0;
+        }
+        Ok(())
+    }
+}
+
+impl<W> Write for EncodeWriter<W>
+where
+    W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        self.encoded.clear();
+        self.encoded
+            .reserve(max_encoded_len(buf.len(), self.line_length));
+        self.col = encode_into(
+            buf,
+            self.col,
+            self.line_length,
+            LineEnding::CrLf,
+            EscapeSet::none(),
+            false,
+            &mut self.encoded,
+        );
+        if let Some(whitespace) = self.pending_whitespace.take() {
+            self.writer.write_all(&[whitespace])?;
         }
+        if self.col > 0 && matches!(self.encoded.last(), Some(&SPACE) | Some(&TAB)) {
+            self.pending_whitespace = self.encoded.pop();
+        }
+        self.writer.write_all(&self.encoded)?;
+        self.checksum.update_with_slice(buf);
+        Ok(buf.len())
     }
 
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Encodes the input buffer and writes it to the writer.
+///
+/// Lines are wrapped with a maximum of `line_length` characters per line.
+/// A SPACE or TAB at the start or the end of a line is escaped, so it survives transports that
+/// trim whitespace. As the data may continue in the next buffer, a SPACE or TAB that ends a
+/// short last line is not escaped; `encode_stream` and `EncodeWriter` escape it.
+/// Does not include the header and footer lines.
+/// Only `encode_stream` and `encode_file` produce the headers in the output.
+/// Pass `EncodeState::start()` for the first buffer, and the returned state for the next buffer.
+///
+/// A two-byte sequence (an escaped character, or a doubled dot at the start of a line) is
+/// never split by a line ending, also not across buffers: the line ending is written after
+/// the sequence, so such a line can be one character longer than `line_length`.
+pub fn encode_buffer<W>(
+    input: &[u8],
+    state: EncodeState,
+    line_length: u16,
+    writer: W,
+) -> Result<EncodeState, EncodeError>
+where
+    W: Write,
+{
+    let mut writer = writer;
+    let (v, state) = encode_to_vec(input, state, line_length);
+    writer.write_all(&v)?;
+    Ok(state)
+}
+
+/// Encodes the input buffer and returns the encoded bytes, together with the new state.
+///
+/// Same as `encode_buffer`, but without the need for a writer.
+///
+/// # Example
+/// ```rust
+/// let (encoded, state) = yenc::encode_to_vec(b"yEnc", yenc::EncodeState::start(), 128);
+/// assert_eq!(b"\xa3o\x98\x8d", encoded.as_slice());
+/// assert_eq!(4, state.col());
+/// ```
+pub fn encode_to_vec(input: &[u8], state: EncodeState, line_length: u16) -> (Vec<u8>, EncodeState) {
+    let mut v = Vec::<u8>::with_capacity(max_encoded_len(input.len(), line_length));
+    let col = encode_into(
+        input,
+        state.col(),
+        line_length,
+        LineEnding::CrLf,
+        EscapeSet::none(),
+        false,
+        &mut v,
+    );
+    (v, EncodeState::at_col(col))
+}
+
+/// Encodes the input buffer and appends the encoded bytes to `v`.
+/// Returns the new column offset.
+#[cfg(not(feature = "simd"))]
+fn encode_into(
+    input: &[u8],
+    col: u16,
+    line_length: u16,
+    line_ending: LineEnding,
+    escape: EscapeSet,
+    end_of_data: bool,
+    v: &mut Vec<u8>,
+) -> u16 {
+    encode_into_scalar(input, col, line_length, line_ending, escape, end_of_data, v)
+}
+
+#[cfg(feature = "simd")]
+use super::simd::encode_into;
+
+/// Encodes the input buffer byte by byte and appends the encoded bytes to `v`.
+/// With `end_of_data`, the input ends the data, so a SPACE or TAB in the last byte is escaped,
+/// as it ends the last line.
+/// Returns the new column offset.
+pub(crate) fn encode_into_scalar(
+    input: &[u8],
+    col: u16,
+    line_length: u16,
+    line_ending: LineEnding,
+    escape: EscapeSet,
+    end_of_data: bool,
+    v: &mut Vec<u8>,
+) -> u16 {
+    encode_with(
+        input,
+        col,
+        line_length,
+        line_ending.as_str().as_bytes(),
+        |encoded_byte| escape.contains(encoded_byte),
+        end_of_data,
+        |byte| v.push(byte),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::constants::{CR, ESCAPE, LF, SPACE, TAB};
+    use super::{
+        encode_buffer, encode_to_vec, plan_parts, plan_parts_by_size, verify_chunk, EncodeOptions,
+        EncodeState, EncodeSummary, EncodeWriter, EscapeSet, LineEnding,
+    };
+    use crate::buffer::max_encoded_len;
+    use crate::crc32::crc32;
+    use crate::errors::EncodeError;
+    use std::io::Cursor;
+    use std::io::Write;
+
     #[test]
     fn test_encode_buffer() {
         let buffer = (0..256u16).map(|c| c as u8).collect::<Vec<u8>>();
@@ -345,11 +1188,569 @@ mod tests {
                        4, 5, 6, 7, 8, 9, 61, 74, 11, 12, 61, 77, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 
                        24, 25, 26, 27, 28, 29, 30, 31, 32, 33, 34, 35, 36, 37, 13, 10, 38, 39, 40, 41];
         let mut encoded = Vec::<u8>::new();
-        let result = encode_buffer(&buffer, 0, 128, &mut encoded);
+        let result = encode_buffer(&buffer, EncodeState::start(), 128, &mut encoded);
         assert!(result.is_ok());
         assert_eq!(encoded.as_slice(), &EXPECTED[..]);
     }
 
+    #[test]
+    fn test_encode_to_vec() {
+        let buffer = (0..256u16).map(|c| c as u8).collect::<Vec<u8>>();
+        let (_, state) = encode_to_vec(&buffer[..10], EncodeState::start(), 128);
+        assert_eq!(10, state.col());
+        let mut encoded = Vec::<u8>::new();
+        let new_state = encode_buffer(&buffer, state, 128, &mut encoded).unwrap();
+        assert_eq!((encoded, new_state), encode_to_vec(&buffer, state, 128));
+    }
+
+    #[test]
+    fn encode_writer_chunks() {
+        let buffer = (0..1000u16).map(|c| c as u8).collect::<Vec<u8>>();
+        let mut writer = EncodeWriter::new(Vec::new(), 64).unwrap();
+        for chunk in buffer.chunks(7) {
+            writer.write_all(chunk).unwrap();
+        }
+        assert_eq!(1000, writer.checksum().num_bytes());
+        let encoded = writer.finish().unwrap();
+
+        let (mut expected, _) = encode_to_vec(&buffer, EncodeState::start(), 64);
+        expected.extend_from_slice(b"\r\n");
+        assert_eq!(expected, encoded);
+    }
+
+    #[test]
+    fn encode_writer_invalid_line_length() {
+        assert_eq!(
+            Err(EncodeError::InvalidLineLength),
+            EncodeWriter::new(Vec::new(), 0).map(|_| ())
+        );
+    }
+
+    #[test]
+    fn encode_writer_footer() {
+        let mut writer = EncodeWriter::new(Vec::new(), 128).unwrap();
+        writer.write_all(b"yEnc").unwrap();
+        let encoded = writer.finish_with_footer().unwrap();
+
+        let mut expected = b"\xa3o\x98\x8d\r\n".to_vec();
+        expected
+            .extend_from_slice(format!("=yend size=4 crc32={:08x}\r\n", crc32(b"yEnc")).as_bytes());
+        assert_eq!(expected, encoded);
+    }
+
+    #[test]
+    fn encode_line_length_above_255() {
+        let buffer = [b'a'; 1000];
+        let (encoded, state) = encode_to_vec(&buffer, EncodeState::start(), 300);
+        let lines = encoded.split(|&b| b == LF).collect::<Vec<&[u8]>>();
+        assert_eq!(302, lines[0].len() + 1);
+        assert_eq!(100, state.col());
+    }
+
+    #[test]
+    fn encode_line_length_u16_max() {
+        let buffer = vec![0xd6u8; 70_000];
+        let (encoded, state) = encode_to_vec(&buffer, EncodeState::start(), u16::MAX);
+        assert_eq!(&encoded[65_536..65_538], b"\r\n");
+        assert_eq!(8_928, state.col());
+
+        let encoded = EncodeOptions::new()
+            .line_length(u16::MAX)
+            .encode_bytes(&buffer, "a.bin")
+            .unwrap();
+        let (decoded, _) = crate::decode_message(&encoded).unwrap();
+        assert_eq!(buffer, decoded);
+    }
+
+    #[test]
+    fn single_part_invalid_range() {
+        let mut output = Vec::new();
+        assert_eq!(
+            Err(EncodeError::PartOffsetsInvalidRange),
+            EncodeOptions::new()
+                .begin(10)
+                .encode_stream(Cursor::new(b"abc"), &mut output, 3, "a.bin")
+                .map(|_| ())
+        );
+        assert_eq!(
+            Err(EncodeError::PartOffsetsInvalidRange),
+            EncodeOptions::new()
+                .begin(3)
+                .end(1)
+                .encode_bytes(b"abc", "a.bin")
+                .map(|_| ())
+        );
+        assert!(EncodeOptions::new()
+            .begin(3)
+            .encode_bytes(b"abc", "a.bin")
+            .is_ok());
+        assert!(EncodeOptions::new().encode_bytes(b"", "a.bin").is_ok());
+    }
+
+    #[test]
+    fn extra_header_field() {
+        let encode_options = EncodeOptions::new()
+            .parts(2)
+            .part(1)
+            .begin(1)
+            .end(4)
+            .extra_header_field("desc", "a=b")
+            .extra_header_field("x1", "");
+        let mut output = Vec::new();
+        encode_options
+            .encode_stream(Cursor::new(b"yEnc"), &mut output, 4, "a.bin")
+            .unwrap();
+        assert!(output.starts_with(
+            b"=ybegin part=1 total=2 line=128 size=4 desc=a=b x1= name=a.bin\r\n=ypart begin=1 end=4\r\n"
+        ));
+    }
+
+    #[test]
+    fn extra_header_field_invalid() {
+        for (key, value) in [
+            ("", "a"),
+            ("Desc", "a"),
+            ("de-sc", "a"),
+            ("name", "a"),
+            ("begin", "1"),
+            ("crc32", "0"),
+            ("pcrc32", "0"),
+            ("end", "1"),
+            ("desc", "a b"),
+            ("desc", "a\r\nb"),
+            ("desc", "a\n"),
+        ] {
+            let encode_options = EncodeOptions::new().extra_header_field(key, value);
+            assert_eq!(
+                Err(EncodeError::InvalidHeaderField {
+                    key: key.to_string()
+                }),
+                encode_options.check_options()
+            );
+        }
+    }
+
+    #[test]
+    fn encode_options_invalid_line_length() {
+        let encode_options = EncodeOptions::new().line_length(0);
+        assert_eq!(
+            Err(EncodeError::InvalidLineLength),
+            encode_options.check_options()
+        );
+        let mut input = Cursor::new(b"yEnc");
+        assert_eq!(
+            Err(EncodeError::InvalidLineLength),
+            encode_options.encode_stream(&mut input, Vec::new(), 4, "a.bin")
+        );
+    }
+
+    #[test]
+    fn encode_to_vec_worst_case_capacity() {
+        // every byte encodes to an escaped NUL
+        let input = [214u8; 1000];
+        for line_length in [1, 2, 3, 128, 2000] {
+            let (encoded, _) = encode_to_vec(&input, EncodeState::start(), line_length);
+            assert!(encoded.len() <= max_encoded_len(input.len(), line_length));
+            assert_eq!(
+                max_encoded_len(input.len(), line_length),
+                encoded.capacity()
+            );
+        }
+    }
+
+    #[test]
+    fn plan_parts_remainder() {
+        assert_eq!(
+            vec![(1, 1, 33), (2, 34, 66), (3, 67, 100)],
+            plan_parts(100, 3)
+        );
+        assert_eq!(vec![(1, 1, 100)], plan_parts(100, 1));
+        assert_eq!(vec![(1, 1, 0)], plan_parts(0, 1));
+        assert!(plan_parts(100, 0).is_empty());
+        assert!(plan_parts(2, 3).is_empty());
+    }
+
+    #[test]
+    fn plan_parts_by_size_remainder() {
+        assert_eq!(
+            vec![
+                (1, 1, 768_000),
+                (2, 768_001, 1_536_000),
+                (3, 1_536_001, 2_000_000)
+            ],
+            plan_parts_by_size(2_000_000, 768_000)
+        );
+        assert_eq!(vec![(1, 1, 40), (2, 41, 80)], plan_parts_by_size(80, 40));
+        assert_eq!(vec![(1, 1, 10)], plan_parts_by_size(10, 100));
+        assert_eq!(vec![(1, 1, 0)], plan_parts_by_size(0, 100));
+        assert!(plan_parts_by_size(100, 0).is_empty());
+        assert_eq!(
+            vec![(1, 1, u64::MAX)],
+            plan_parts_by_size(u64::MAX, u64::MAX)
+        );
+        assert_eq!(
+            vec![(1, 1, 1 << 63), (2, (1 << 63) + 1, u64::MAX)],
+            plan_parts_by_size(u64::MAX, 1 << 63)
+        );
+        assert!(plan_parts_by_size(u64::MAX, 1).is_empty());
+        assert!(plan_parts_by_size(u64::from(u32::MAX) + 1, 1).is_empty());
+    }
+
+    #[test]
+    fn encode_bytes() {
+        let data = (0..100u8).collect::<Vec<u8>>();
+        let mut expected = Vec::new();
+        EncodeOptions::new()
+            .encode_stream(Cursor::new(&data), &mut expected, 100, "data.bin")
+            .unwrap();
+        assert_eq!(
+            expected,
+            EncodeOptions::new()
+                .encode_bytes(&data, "data.bin")
+                .unwrap()
+        );
+
+        let encode_options = EncodeOptions::new().parts(2).part(2).begin(51).end(100);
+        let mut expected = Vec::new();
+        encode_options
+            .encode_stream(Cursor::new(&data), &mut expected, 100, "data.bin")
+            .unwrap();
+        assert_eq!(
+            expected,
+            encode_options.encode_bytes(&data, "data.bin").unwrap()
+        );
+        assert!(EncodeOptions::new()
+            .parts(2)
+            .part(2)
+            .begin(51)
+            .end(101)
+            .encode_bytes(&data, "data.bin")
+            .is_err());
+    }
+
+    #[test]
+    fn encode_file_parallel() {
+        let data = (0..10_000u32).map(|c| (c % 251) as u8).collect::<Vec<u8>>();
+        let dir = std::env::temp_dir().join("yenc_encode_file_parallel");
+        std::fs::create_dir_all(&dir).unwrap();
+        let input_path = dir.join("data.bin");
+        std::fs::write(&input_path, &data).unwrap();
+
+        let encode_options = EncodeOptions::new();
+        let expected = encode_options
+            .encode_file_multipart(&input_path, 7, |part| {
+                std::fs::File::create(dir.join(format!("expected.{:03}", part)))
+            })
+            .unwrap();
+        let part_infos = encode_options
+            .encode_file_parallel(&input_path, 7, |part| {
+                std::fs::File::create(dir.join(format!("parallel.{:03}", part)))
+            })
+            .unwrap();
+        assert_eq!(expected, part_infos);
+        for part in 1..=7 {
+            assert_eq!(
+                std::fs::read(dir.join(format!("expected.{:03}", part))).unwrap(),
+                std::fs::read(dir.join(format!("parallel.{:03}", part))).unwrap()
+            );
+        }
+        assert_eq!(
+            Err(EncodeError::InvalidPartCount),
+            encode_options.encode_file_parallel(&input_path, 0, |_| Ok(Vec::new()))
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn encode_read_buffer_size() {
+        let data = (0..1000u16).map(|c| c as u8).collect::<Vec<u8>>();
+        let mut expected = Vec::new();
+        EncodeOptions::new()
+            .encode_stream(Cursor::new(&data), &mut expected, 1000, "data.bin")
+            .unwrap();
+        let mut output = Vec::new();
+        EncodeOptions::new()
+            .read_buffer_size(7)
+            .encode_stream(Cursor::new(&data), &mut output, 1000, "data.bin")
+            .unwrap();
+        assert_eq!(expected, output);
+        assert_eq!(
+            Err(EncodeError::InvalidReadBufferSize),
+            EncodeOptions::new().read_buffer_size(0).check_options()
+        );
+    }
+
+    #[test]
+    fn encode_verify() {
+        let data = (0..1000u16).map(|c| (c % 7) as u8 + 3).collect::<Vec<u8>>();
+        let mut expected = Vec::new();
+        EncodeOptions::new()
+            .line_length(10)
+            .encode_stream(Cursor::new(&data), &mut expected, 1000, "data.bin")
+            .unwrap();
+        let mut output = Vec::new();
+        EncodeOptions::new()
+            .line_length(10)
+            .verify(true)
+            .encode_stream(Cursor::new(&data), &mut output, 1000, "data.bin")
+            .unwrap();
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn encode_verify_chunk() {
+        let mut buffers = (Vec::new(), Vec::new());
+        assert_eq!(Ok(()), verify_chunk(&[4, 4], b"..", false, 0, &mut buffers));
+        assert_eq!(Ok(()), verify_chunk(&[4], b"..", true, 0, &mut buffers));
+        assert_eq!(
+            Err(EncodeError::VerificationFailed { offset: 11 }),
+            verify_chunk(&[4, 5], b"..", false, 10, &mut buffers)
+        );
+    }
+
+    #[test]
+    fn encode_multipart_total() {
+        let data = [b'a'; 100];
+        let encode_options = EncodeOptions::new().parts(3).part(2).begin(41).end(80);
+        let mut output = Vec::new();
+        let summary = encode_options
+            .encode_stream(Cursor::new(&data), &mut output, 100, "data.bin")
+            .unwrap();
+        assert_eq!(
+            EncodeSummary {
+                bytes: 40,
+                crc32: crc32(&data[40..80]),
+                part: 2,
+                encoded_bytes: output.len() as u64
+            },
+            summary
+        );
+        assert!(output.starts_with(b"=ybegin part=2 total=3 line=128 size=100 name=data.bin\r\n"));
+    }
+
+    #[test]
+    fn encode_stream_sequential() {
+        let data = (0..1000u16).map(|c| c as u8).collect::<Vec<u8>>();
+        let encode_options = EncodeOptions::new();
+        let mut expected = Vec::new();
+        encode_options
+            .encode_stream(Cursor::new(&data), &mut expected, 1000, "data.bin")
+            .unwrap();
+        let mut output = Vec::new();
+        encode_options
+            .encode_stream_sequential(&data[..], &mut output, 1000, "data.bin")
+            .unwrap();
+        assert_eq!(expected, output);
+
+        let encode_options = EncodeOptions::new().parts(2).part(2).begin(501).end(1000);
+        let mut expected = Vec::new();
+        encode_options
+            .encode_stream(Cursor::new(&data), &mut expected, 1000, "data.bin")
+            .unwrap();
+        let mut output = Vec::new();
+        encode_options
+            .encode_stream_sequential(&data[500..], &mut output, 1000, "data.bin")
+            .unwrap();
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn encode_stream_unknown_size() {
+        let data = (0..1000u16).map(|c| c as u8).collect::<Vec<u8>>();
+        let encode_options = EncodeOptions::new();
+        let mut output = Vec::new();
+        let summary = encode_options
+            .encode_stream_unknown_size(&data[..], &mut output, "data.bin")
+            .unwrap();
+        assert_eq!(
+            EncodeSummary {
+                bytes: 1000,
+                crc32: crc32(&data),
+                part: 1,
+                encoded_bytes: output.len() as u64
+            },
+            summary
+        );
+        assert!(output.starts_with(b"=ybegin line=128 name=data.bin\r\n"));
+        let footer = format!("=yend size=1000 crc32={:08x}\r\n", crc32(&data));
+        assert!(output.ends_with(footer.as_bytes()));
+
+        let mut expected = Vec::new();
+        encode_options
+            .encode_stream(Cursor::new(&data), &mut expected, 1000, "data.bin")
+            .unwrap();
+        let header_end = expected.iter().position(|&b| b == LF).unwrap();
+        assert_eq!(
+            &expected[header_end..],
+            &output[output.iter().position(|&b| b == LF).unwrap()..]
+        );
+    }
+
+    // input bytes that encode to 'a', '=@' (escaped NUL) and '.'
+    const A: u8 = b'a' - 42;
+    const NUL_ESCAPED: u8 = 214;
+    const DOT: u8 = b'.' - 42;
+
+    #[test]
+    fn encode_escape_pair_at_line_end() {
+        let input = [A, A, A, NUL_ESCAPED, A];
+        let (encoded, state) = encode_to_vec(&input, EncodeState::start(), 4);
+        assert_eq!(b"aaa=@\r\na", encoded.as_slice());
+        assert_eq!(1, state.col());
+
+        let (encoded, state) = encode_to_vec(&input, EncodeState::start(), 3);
+        assert_eq!(b"aaa\r\n=@a\r\n", encoded.as_slice());
+        assert_eq!(0, state.col());
+    }
+
+    #[test]
+    fn encode_escape_pair_at_chunk_end() {
+        let mut encoded = Vec::new();
+        let state =
+            encode_buffer(&[A, A, NUL_ESCAPED], EncodeState::start(), 4, &mut encoded).unwrap();
+        assert_eq!(0, state.col());
+        let state = encode_buffer(&[NUL_ESCAPED, A], state, 4, &mut encoded).unwrap();
+        assert_eq!(3, state.col());
+        assert_eq!(b"aa=@\r\n=@a", encoded.as_slice());
+    }
+
+    #[test]
+    fn encode_dot_pair_at_line_start() {
+        let (encoded, state) = encode_to_vec(&[DOT, DOT, DOT], EncodeState::start(), 1);
+        assert_eq!(b"..\r\n..\r\n..\r\n", encoded.as_slice());
+        assert_eq!(0, state.col());
+
+        let (encoded, _) = encode_to_vec(&[DOT, DOT, DOT], EncodeState::start(), 3);
+        assert_eq!(b"...\r\n..", encoded.as_slice());
+
+        let mut encoded = Vec::new();
+        let state = encode_buffer(&[A, A], EncodeState::start(), 2, &mut encoded).unwrap();
+        encode_buffer(&[DOT], state, 2, &mut encoded).unwrap();
+        assert_eq!(b"aa\r\n..\r\n", encoded.as_slice());
+    }
+
+    /// Encodes the input with `escape_extra` and returns the encoded data line.
+    fn encode_escaped(input: &[u8], escape: EscapeSet) -> Vec<u8> {
+        let mut output = Vec::new();
+        EncodeOptions::new()
+            .escape_extra(escape)
+            .encode_stream(Cursor::new(input), &mut output, input.len() as u64, "a.bin")
+            .unwrap();
+        output.split(|&b| b == LF).nth(1).unwrap().to_vec()
+    }
+
+    #[test]
+    fn escape_tab() {
+        let escape = EscapeSet::none().tab(true);
+        assert_eq!(vec![ESCAPE, 0x49, CR], encode_escaped(&[214 + TAB], escape));
+        assert_eq!(
+            vec![A + 42, ESCAPE, 0x49, A + 42, CR],
+            encode_escaped(&[A, 214 + TAB, A], escape)
+        );
+        assert_eq!(
+            vec![A + 42, TAB, A + 42, CR],
+            encode_escaped(&[A, 214 + TAB, A], EscapeSet::none())
+        );
+    }
+
+    #[test]
+    fn escape_space() {
+        let escape = EscapeSet::none().space(true);
+        assert_eq!(
+            vec![ESCAPE, 0x60, CR],
+            encode_escaped(&[214 + SPACE], escape)
+        );
+        assert_eq!(
+            vec![A + 42, ESCAPE, 0x60, A + 42, CR],
+            encode_escaped(&[A, 214 + SPACE, A], escape)
+        );
+        assert_eq!(
+            vec![A + 42, SPACE, A + 42, CR],
+            encode_escaped(&[A, 214 + SPACE, A], EscapeSet::none())
+        );
+    }
+
+    #[test]
+    fn escape_space_at_line_edges() {
+        const S: u8 = SPACE.wrapping_sub(42);
+        let (encoded, state) = encode_to_vec(&[S, A, A, S, A, S], EncodeState::start(), 4);
+        assert_eq!(b"=`aa\r\n=`a=`\r\n", encoded.as_slice());
+        assert_eq!(0, state.col());
+
+        let (encoded, _) = encode_to_vec(&[A, S, A], EncodeState::start(), 4);
+        assert_eq!(b"a a", encoded.as_slice());
+    }
+
+    #[test]
+    fn escape_space_at_end_of_data() {
+        // 0xF6 encodes to a SPACE, which would end the last line
+        let data = [A, 0xF6];
+        let expected_body = b"a=`\r\n=yend ";
+        let encode_options = EncodeOptions::new();
+        let output = encode_options.encode_bytes(&data, "a.bin").unwrap();
+        assert!(output
+            .windows(expected_body.len())
+            .any(|w| w == expected_body));
+
+        let mut output = Vec::new();
+        encode_options
+            .read_buffer_size(1)
+            .encode_stream_unknown_size(&data[..], &mut output, "a.bin")
+            .unwrap();
+        assert!(output
+            .windows(expected_body.len())
+            .any(|w| w == expected_body));
+
+        let mut writer = EncodeWriter::new(Vec::new(), 128).unwrap();
+        writer.write_all(&data[..1]).unwrap();
+        writer.write_all(&data[1..]).unwrap();
+        writer.write_all(&data).unwrap();
+        writer.write_all(&[]).unwrap();
+        let output = writer.finish().unwrap();
+        assert_eq!(b"a a=`\r\n", output.as_slice());
+    }
+
+    #[test]
+    fn escape_dot() {
+        let escape = EscapeSet::none().dot(true);
+        assert_eq!(b"=n=n\r".to_vec(), encode_escaped(&[DOT, DOT], escape));
+        assert_eq!(
+            b"...\r".to_vec(),
+            encode_escaped(&[DOT, DOT], EscapeSet::none())
+        );
+    }
+
+    #[test]
+    fn escape_extra_roundtrip() {
+        let data = (0..1000u16).map(|c| c as u8).collect::<Vec<u8>>();
+        let escape = EscapeSet::none().tab(true).space(true).dot(true);
+        let mut output = Vec::new();
+        EncodeOptions::new()
+            .line_length(10)
+            .escape_extra(escape)
+            .verify(true)
+            .encode_stream(Cursor::new(&data), &mut output, 1000, "data.bin")
+            .unwrap();
+        assert!(!output.contains(&TAB));
+    }
+
+    #[test]
+    fn no_line_starts_with_escaped_y() {
+        // `=y` at the start of a line would look like a `=ybegin`, `=ypart` or `=yend` line
+        let escape = EscapeSet::none().tab(true).space(true).dot(true);
+        for escape in [EscapeSet::none(), escape] {
+            for byte in 0..=255u8 {
+                let output = EncodeOptions::new()
+                    .line_length(4)
+                    .escape_extra(escape)
+                    .encode_bytes(&[byte; 20], "data.bin")
+                    .unwrap();
+                let mut lines = output.split(|&c| c == LF).skip(1);
+                assert!(lines.all(|line| !line.starts_with(b"=y") || line.starts_with(b"=yend ")));
+            }
+        }
+    }
+
     #[test]
     fn encode_options_invalid_parts() {
         let encode_options = EncodeOptions::new().parts(2).begin(1).end(38400);
@@ -377,4 +1778,46 @@ mod tests {
         let vr = encode_options.check_options();
         assert!(vr.is_err());
     }
+
+    #[test]
+    fn encode_file_crc32_in_last_part() {
+        let data = (0..100u8).collect::<Vec<u8>>();
+        let encode_options = EncodeOptions::new()
+            .parts(2)
+            .begin(51)
+            .end(100)
+            .file_crc32(0x1234_abcd);
+
+        let mut output = Vec::new();
+        encode_options
+            .clone()
+            .part(1)
+            .encode_stream(Cursor::new(&data), &mut output, 100, "data.bin")
+            .unwrap();
+        assert!(!String::from_utf8_lossy(&output).contains(" crc32="));
+
+        let mut output = Vec::new();
+        encode_options
+            .part(2)
+            .encode_stream(Cursor::new(&data), &mut output, 100, "data.bin")
+            .unwrap();
+        assert!(String::from_utf8_lossy(&output).ends_with(" crc32=1234abcd\r\n"));
+    }
+
+    #[test]
+    fn encode_line_ending_lf() {
+        let data = (0..200u8).collect::<Vec<u8>>();
+        let mut output = Vec::new();
+        EncodeOptions::new()
+            .line_length(64)
+            .line_ending(LineEnding::Lf)
+            .encode_stream(Cursor::new(&data), &mut output, 200, "data.bin")
+            .unwrap();
+        assert!(!output.contains(&CR));
+        assert!(output.starts_with(b"=ybegin line=64 size=200 name=data.bin\n"));
+        assert!(output.ends_with(b"\n"));
+        assert!(output
+            .split(|&b| b == LF)
+            .all(|line| line.len() <= 65 || line.starts_with(b"=y")));
+    }
 }
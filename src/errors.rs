@@ -1,9 +1,16 @@
-use std::convert::From;
-use std::fmt;
+use core::fmt;
+#[cfg(feature = "std")]
 use std::io;
 
 /// Error enum for errors that can be encountered while decoding.
+///
+/// Errors can be compared with `==`. Two `IoError` values are equal when their
+/// `io::ErrorKind` is equal.
+///
+/// Some variants only exist with the `std` feature, and variants may be added in the future,
+/// so the enum is `#[non_exhaustive]`.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum DecodeError {
     /// Fewer or more bytes than expected.
     IncompleteData {
@@ -13,20 +20,79 @@ pub enum DecodeError {
         actual_size: usize,
     },
     /// The header or footer line contains unexpected characters or is incomplete.
+    #[cfg(feature = "std")]
     InvalidHeader {
         /// the header line
         line: String,
         /// the position in the line where the parsing error occurred
         position: usize,
     },
+    /// A `size`, `begin` or `end` value in a header line is larger than the maximum size on
+    /// this platform (`usize::MAX`).
+    #[cfg(feature = "std")]
+    SizeOverflow {
+        /// the value, as specified in the header line
+        value: String,
+    },
+    /// A keyword occurs more than once in a header line (strict mode only).
+    #[cfg(feature = "std")]
+    DuplicateKeyword {
+        /// the repeated keyword
+        keyword: String,
+    },
     /// CRC32 checksum of the part is not the expected checksum.
     InvalidChecksum,
+    /// Non-whitespace data follows the `=yend` line (strict mode only).
+    TrailingData {
+        /// the number of bytes after the footer line
+        bytes: usize,
+    },
+    /// The stream ended before the `=yend` line was found (strict mode only).
+    MissingFooter,
+    /// The stream contains no `=ybegin` line.
+    NoYencData,
+    /// An escape character (`=`) is followed by a line break instead of the escaped byte.
+    DanglingEscape,
+    /// The decoded data exceeds the maximum output size.
+    OutputTooLarge {
+        /// the maximum number of decoded bytes, see `DecodeOptions::max_output_bytes`
+        limit: usize,
+    },
+    /// The filename in the header is an absolute path or refers to a parent directory.
+    #[cfg(feature = "std")]
+    UnsafeFilename {
+        /// the filename, as specified in the header
+        name: String,
+    },
+    /// The header has no filename, or an empty filename, and no filename override is set.
+    MissingFilename,
+    /// The output buffer is too small for the decoded data.
+    BufferTooSmall {
+        /// the number of bytes needed for the decoded data
+        needed: usize,
+    },
     /// An I/O error occurred.
+    #[cfg(feature = "std")]
     IoError(io::Error),
+    /// An error in a header or data line, with the offset of that line in the stream.
+    #[cfg(feature = "std")]
+    At {
+        /// the offset of the start of the line, from the start of the stream
+        offset: usize,
+        /// the error in the line
+        source: Box<DecodeError>,
+    },
 }
 
 /// Error enum for errors that can be encountered when validating the encode options or while encoding.
+///
+/// Errors can be compared with `==`. Two `IoError` values are equal when their
+/// `io::ErrorKind` is equal.
+///
+/// Some variants only exist with the `std` feature, and variants may be added in the future,
+/// so the enum is `#[non_exhaustive]`.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum EncodeError {
     /// Multiple parts (parts > 1), but no part number specified
     PartNumberMissing,
@@ -34,24 +100,188 @@ pub enum EncodeError {
     PartBeginOffsetMissing,
     /// Multiple parts (parts > 1), but no end offset specified
     PartEndOffsetMissing,
-    /// Multiple parts (parts > 1), and begin offset larger than end offset
+    /// Begin offset larger than end offset (for a single part, the end offset defaults to the
+    /// input size)
     PartOffsetsInvalidRange,
+    /// The number of parts is 0 or larger than the input size
+    InvalidPartCount,
+    /// The line length is 0
+    InvalidLineLength,
+    /// The read buffer size is 0
+    InvalidReadBufferSize,
+    /// An extra header field has an invalid key, or a value with a line break
+    #[cfg(feature = "std")]
+    InvalidHeaderField {
+        /// the key of the field
+        key: String,
+    },
+    /// The encoded data does not decode to the input data.
+    VerificationFailed {
+        /// the offset of the first byte that differs, relative to the start of the encoded data
+        offset: u64,
+    },
+    /// The output buffer is too small for the encoded data.
+    BufferTooSmall {
+        /// the number of bytes needed for the encoded data
+        needed: usize,
+    },
     /// I/O Error
+    #[cfg(feature = "std")]
     IoError(io::Error),
 }
 
+#[cfg(feature = "std")]
 impl From<io::Error> for DecodeError {
     fn from(error: io::Error) -> DecodeError {
         DecodeError::IoError(error)
     }
 }
 
+#[cfg(feature = "std")]
 impl From<io::Error> for EncodeError {
     fn from(error: io::Error) -> EncodeError {
         EncodeError::IoError(error)
     }
 }
 
+/// Returns the I/O error of an `IoError`, other errors are wrapped in an I/O error of kind
+/// `InvalidData`.
+#[cfg(feature = "std")]
+impl From<DecodeError> for io::Error {
+    fn from(error: DecodeError) -> io::Error {
+        match error {
+            DecodeError::IoError(err) => err,
+            err => io::Error::new(io::ErrorKind::InvalidData, err),
+        }
+    }
+}
+
+/// Returns the I/O error of an `IoError`, other errors are wrapped in an I/O error of kind
+/// `InvalidData`.
+#[cfg(feature = "std")]
+impl From<EncodeError> for io::Error {
+    fn from(error: EncodeError) -> io::Error {
+        match error {
+            EncodeError::IoError(err) => err,
+            err => io::Error::new(io::ErrorKind::InvalidData, err),
+        }
+    }
+}
+
+impl PartialEq for DecodeError {
+    fn eq(&self, other: &DecodeError) -> bool {
+        match (self, other) {
+            (
+                DecodeError::IncompleteData {
+                    expected_size,
+                    actual_size,
+                },
+                DecodeError::IncompleteData {
+                    expected_size: other_expected_size,
+                    actual_size: other_actual_size,
+                },
+            ) => expected_size == other_expected_size && actual_size == other_actual_size,
+            #[cfg(feature = "std")]
+            (
+                DecodeError::InvalidHeader { line, position },
+                DecodeError::InvalidHeader {
+                    line: other_line,
+                    position: other_position,
+                },
+            ) => line == other_line && position == other_position,
+            #[cfg(feature = "std")]
+            (
+                DecodeError::SizeOverflow { value },
+                DecodeError::SizeOverflow { value: other_value },
+            ) => value == other_value,
+            #[cfg(feature = "std")]
+            (
+                DecodeError::DuplicateKeyword { keyword },
+                DecodeError::DuplicateKeyword {
+                    keyword: other_keyword,
+                },
+            ) => keyword == other_keyword,
+            (DecodeError::InvalidChecksum, DecodeError::InvalidChecksum) => true,
+            (
+                DecodeError::TrailingData { bytes },
+                DecodeError::TrailingData { bytes: other_bytes },
+            ) => bytes == other_bytes,
+            (DecodeError::MissingFooter, DecodeError::MissingFooter) => true,
+            (DecodeError::NoYencData, DecodeError::NoYencData) => true,
+            (DecodeError::DanglingEscape, DecodeError::DanglingEscape) => true,
+            (
+                DecodeError::OutputTooLarge { limit },
+                DecodeError::OutputTooLarge { limit: other_limit },
+            ) => limit == other_limit,
+            #[cfg(feature = "std")]
+            (
+                DecodeError::UnsafeFilename { name },
+                DecodeError::UnsafeFilename { name: other_name },
+            ) => name == other_name,
+            (DecodeError::MissingFilename, DecodeError::MissingFilename) => true,
+            (
+                DecodeError::BufferTooSmall { needed },
+                DecodeError::BufferTooSmall {
+                    needed: other_needed,
+                },
+            ) => needed == other_needed,
+            #[cfg(feature = "std")]
+            (DecodeError::IoError(err), DecodeError::IoError(other_err)) => {
+                err.kind() == other_err.kind()
+            }
+            #[cfg(feature = "std")]
+            (
+                DecodeError::At { offset, source },
+                DecodeError::At {
+                    offset: other_offset,
+                    source: other_source,
+                },
+            ) => offset == other_offset && source == other_source,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for DecodeError {}
+
+impl PartialEq for EncodeError {
+    fn eq(&self, other: &EncodeError) -> bool {
+        match (self, other) {
+            (EncodeError::PartNumberMissing, EncodeError::PartNumberMissing) => true,
+            (EncodeError::PartBeginOffsetMissing, EncodeError::PartBeginOffsetMissing) => true,
+            (EncodeError::PartEndOffsetMissing, EncodeError::PartEndOffsetMissing) => true,
+            (EncodeError::PartOffsetsInvalidRange, EncodeError::PartOffsetsInvalidRange) => true,
+            (EncodeError::InvalidPartCount, EncodeError::InvalidPartCount) => true,
+            (EncodeError::InvalidLineLength, EncodeError::InvalidLineLength) => true,
+            (EncodeError::InvalidReadBufferSize, EncodeError::InvalidReadBufferSize) => true,
+            #[cfg(feature = "std")]
+            (
+                EncodeError::InvalidHeaderField { key },
+                EncodeError::InvalidHeaderField { key: other_key },
+            ) => key == other_key,
+            (
+                EncodeError::VerificationFailed { offset },
+                EncodeError::VerificationFailed {
+                    offset: other_offset,
+                },
+            ) => offset == other_offset,
+            (
+                EncodeError::BufferTooSmall { needed },
+                EncodeError::BufferTooSmall {
+                    needed: other_needed,
+                },
+            ) => needed == other_needed,
+            #[cfg(feature = "std")]
+            (EncodeError::IoError(err), EncodeError::IoError(other_err)) => {
+                err.kind() == other_err.kind()
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Eq for EncodeError {}
+
 impl fmt::Display for DecodeError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
@@ -63,11 +293,38 @@ impl fmt::Display for DecodeError {
                 "Incomplete data: expected size {}, actual size {}",
                 expected_size, actual_size
             ),
+            #[cfg(feature = "std")]
             DecodeError::InvalidHeader { ref line, position } => {
                 write!(f, "Invalid header: \n{}\n{}^", line, " ".repeat(position))
             }
+            #[cfg(feature = "std")]
+            DecodeError::SizeOverflow { ref value } => write!(f, "Size too large: {}", value),
+            #[cfg(feature = "std")]
+            DecodeError::DuplicateKeyword { ref keyword } => {
+                write!(f, "Duplicate keyword: {}", keyword)
+            }
             DecodeError::InvalidChecksum => write!(f, "Invalid checksum"),
+            DecodeError::TrailingData { bytes } => {
+                write!(f, "Trailing data: {} bytes after footer", bytes)
+            }
+            DecodeError::MissingFooter => write!(f, "Missing footer"),
+            DecodeError::NoYencData => write!(f, "No yEnc data: =ybegin line not found"),
+            DecodeError::DanglingEscape => write!(f, "Escape character at end of line"),
+            DecodeError::OutputTooLarge { limit } => {
+                write!(f, "Output too large: more than {} bytes decoded", limit)
+            }
+            #[cfg(feature = "std")]
+            DecodeError::UnsafeFilename { ref name } => write!(f, "Unsafe filename: {}", name),
+            DecodeError::MissingFilename => write!(f, "Missing filename"),
+            DecodeError::BufferTooSmall { needed } => {
+                write!(f, "Buffer too small: {} bytes needed", needed)
+            }
+            #[cfg(feature = "std")]
             DecodeError::IoError(ref err) => write!(f, "I/O error {}", err),
+            #[cfg(feature = "std")]
+            DecodeError::At { offset, ref source } => {
+                write!(f, "{} (line at offset {})", source, offset)
+            }
         }
     }
 }
@@ -85,10 +342,115 @@ impl fmt::Display for EncodeError {
                 write!(f, "Multiple parts, but no end offset specified.")
             }
             EncodeError::PartOffsetsInvalidRange => {
-                write!(f, "Multiple parts, begin offset larger than end offset")
+                write!(f, "Begin offset larger than end offset.")
+            }
+            EncodeError::InvalidPartCount => {
+                write!(f, "Number of parts is 0 or larger than the input size.")
             }
+            EncodeError::InvalidLineLength => write!(f, "Line length is 0."),
+            EncodeError::InvalidReadBufferSize => write!(f, "Read buffer size is 0."),
+            #[cfg(feature = "std")]
+            EncodeError::InvalidHeaderField { ref key } => {
+                write!(f, "Invalid extra header field: {}", key)
+            }
+            EncodeError::VerificationFailed { offset } => {
+                write!(
+                    f,
+                    "Verification failed: decoded data differs at offset {}",
+                    offset
+                )
+            }
+            EncodeError::BufferTooSmall { needed } => {
+                write!(f, "Buffer too small: {} bytes needed", needed)
+            }
+            #[cfg(feature = "std")]
             EncodeError::IoError(ref err) => write!(f, "I/O error {}", err),
         }
     }
 }
-// impl error::Error for DecodeError {}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DecodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DecodeError::IoError(err) => Some(err),
+            DecodeError::At { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for EncodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            EncodeError::IoError(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::{DecodeError, EncodeError};
+    use std::io;
+
+    #[test]
+    fn decode_error_eq() {
+        assert_eq!(DecodeError::InvalidChecksum, DecodeError::InvalidChecksum);
+        assert_ne!(DecodeError::InvalidChecksum, DecodeError::MissingFooter);
+        assert_eq!(
+            DecodeError::TrailingData { bytes: 3 },
+            DecodeError::TrailingData { bytes: 3 }
+        );
+        assert_ne!(
+            DecodeError::TrailingData { bytes: 3 },
+            DecodeError::TrailingData { bytes: 4 }
+        );
+        assert_eq!(
+            DecodeError::IoError(io::Error::new(io::ErrorKind::NotFound, "a")),
+            DecodeError::IoError(io::ErrorKind::NotFound.into())
+        );
+        assert_ne!(
+            DecodeError::IoError(io::ErrorKind::NotFound.into()),
+            DecodeError::IoError(io::ErrorKind::AlreadyExists.into())
+        );
+    }
+
+    #[test]
+    fn decode_error_into_io_error() {
+        let err = io::Error::from(DecodeError::IoError(io::ErrorKind::NotFound.into()));
+        assert_eq!(io::ErrorKind::NotFound, err.kind());
+        let err = io::Error::from(DecodeError::InvalidChecksum);
+        assert_eq!(io::ErrorKind::InvalidData, err.kind());
+        assert_eq!(DecodeError::InvalidChecksum.to_string(), err.to_string());
+        assert_eq!(
+            Some(DecodeError::InvalidChecksum),
+            err.into_inner()
+                .and_then(|inner| inner.downcast::<DecodeError>().ok())
+                .map(|inner| *inner)
+        );
+    }
+
+    #[test]
+    fn encode_error_into_io_error() {
+        let err = io::Error::from(EncodeError::IoError(io::ErrorKind::NotFound.into()));
+        assert_eq!(io::ErrorKind::NotFound, err.kind());
+        let err = io::Error::from(EncodeError::InvalidPartCount);
+        assert_eq!(io::ErrorKind::InvalidData, err.kind());
+        assert_eq!(EncodeError::InvalidPartCount.to_string(), err.to_string());
+    }
+
+    #[test]
+    fn encode_error_eq() {
+        assert_eq!(EncodeError::InvalidPartCount, EncodeError::InvalidPartCount);
+        assert_ne!(
+            EncodeError::InvalidPartCount,
+            EncodeError::PartNumberMissing
+        );
+        assert_eq!(
+            EncodeError::IoError(io::ErrorKind::NotFound.into()),
+            EncodeError::IoError(io::ErrorKind::NotFound.into())
+        );
+    }
+}
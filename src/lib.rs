@@ -1,3 +1,4 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 #![deny(missing_docs)]
 #![deny(rust_2018_compatibility)]
 #![deny(rust_2018_idioms)]
@@ -31,16 +32,55 @@
 //! decode_options.decode_stream(message.as_slice()).unwrap();
 //! ```
 //!
-mod constants;
+//! # Features
+//!
+//! - `std` (default): the file and stream APIs. Without it, the crate is `#![no_std]` and only
+//!   provides `encode_buffer_into`, `decode_buffer_into` and the CRC32 functions, which
+//!   operate on byte slices without allocating.
+//! - `async`: `DecodeOptions::decode_async` and `DecodeOptions::decode_async_to_writer`, for
+//!   decoding from a tokio `AsyncRead`.
+//! - `serde`: `Serialize` and `Deserialize` for `Header`.
+//! - `simd`: encodes 16 bytes at once in `encode_buffer` and `encode_to_vec`, when no escaping
+//!   is needed.
+//! - `cli`: the `ydecode` and `yencode` command line tools
+//!   (`cargo install yenc --features cli`).
+//!
+//! The special byte values and the default line length are available in the `constants`
+//! module.
+//!
+#[cfg(feature = "std")]
+mod assemble;
+mod buffer;
+pub mod constants;
+mod crc32;
+#[cfg(feature = "std")]
 mod decode;
+#[cfg(feature = "std")]
 mod encode;
 mod errors;
+#[cfg(feature = "simd")]
+mod simd;
 
-pub use self::decode::{decode_buffer, DecodeOptions};
-pub use self::encode::{encode_buffer, EncodeOptions};
+#[cfg(feature = "std")]
+pub use self::assemble::MultipartAssembler;
+pub use self::buffer::{decode_buffer_into, encode_buffer_into, max_encoded_len, EncodeState};
+#[cfg(feature = "std")]
+pub use self::crc32::crc32_file;
+pub use self::crc32::{crc32, crc32_combine, Crc32};
+#[cfg(feature = "std")]
+pub use self::decode::{
+    decode_buffer, decode_buffer_partial, decode_message, parse_header, verify_roundtrip,
+    ChecksumPolicy, DecodeOptions, DecodeReader, DecodeResult, Decoder, Header, OnError,
+    StreamDecoder, ValidationReport,
+};
+#[cfg(feature = "std")]
+pub use self::encode::{
+    encode_buffer, encode_to_vec, format_ybegin, format_yend, plan_parts, plan_parts_by_size,
+    EncodeOptions, EncodeSummary, EncodeWriter, EscapeSet, LineEnding, PartInfo,
+};
 pub use self::errors::{DecodeError, EncodeError};
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod tests {
     use super::*;
 
@@ -51,7 +91,7 @@ mod tests {
             b,
             {
                 let mut output = Vec::new();
-                encode_buffer(&b, 0, 128, &mut output).unwrap();
+                encode_buffer(&b, EncodeState::start(), 128, &mut output).unwrap();
                 decode_buffer(&output)
             }
             .unwrap()
@@ -0,0 +1,135 @@
+//! Encoding of 16 bytes at once, enabled with the `simd` feature.
+//!
+//! The bytes of a chunk are handled as the lanes of a `u128` (SIMD within a register), which
+//! is portable and needs neither nightly `std::simd` nor `unsafe` code. Chunks that contain a
+//! critical character, or that would need a dot or line ending in the middle, are encoded by
+//! the scalar encoder, which remains the reference implementation.
+
+use super::buffer::is_whitespace_at_line_edge;
+use super::constants::{CR, DOT, ESCAPE, LF, NUL};
+use super::encode::{encode_into_scalar, EscapeSet, LineEnding};
+
+const LANES: usize = 16;
+const ONES: u128 = u128::from_le_bytes([0x01; LANES]);
+const HIGH_BITS: u128 = u128::from_le_bytes([0x80; LANES]);
+
+/// Returns a word with every lane set to `byte`.
+const fn splat(byte: u8) -> u128 {
+    ONES * byte as u128
+}
+
+/// Returns a nonzero value when any lane of `word` is zero.
+#[inline(always)]
+fn has_zero_lane(word: u128) -> u128 {
+    word.wrapping_sub(ONES) & !word & HIGH_BITS
+}
+
+/// Adds 42 to every lane, wrapping within the lane.
+#[inline(always)]
+fn add_42(word: u128) -> u128 {
+    ((word & !HIGH_BITS) + splat(42)) ^ (word & HIGH_BITS)
+}
+
+/// Returns a nonzero value when any lane of `word` is a critical character.
+#[inline(always)]
+fn has_critical_lane(word: u128) -> u128 {
+    has_zero_lane(word ^ splat(NUL))
+        | has_zero_lane(word ^ splat(LF))
+        | has_zero_lane(word ^ splat(CR))
+        | has_zero_lane(word ^ splat(ESCAPE))
+}
+
+/// Encodes the input buffer and appends the encoded bytes to `v`, like `encode_into_scalar`.
+/// Returns the new column offset.
+pub(crate) fn encode_into(
+    input: &[u8],
+    col: u16,
+    line_length: u16,
+    line_ending: LineEnding,
+    escape: EscapeSet,
+    end_of_data: bool,
+    v: &mut Vec<u8>,
+) -> u16 {
+    if escape != EscapeSet::none() {
+        return encode_into_scalar(input, col, line_length, line_ending, escape, end_of_data, v);
+    }
+    if end_of_data && !input.is_empty() {
+        // the last byte, which ends the last line, is encoded separately
+        let (init, last) = input.split_at(input.len() - 1);
+        let col = encode_into(init, col, line_length, line_ending, escape, false, v);
+        return encode_into_scalar(last, col, line_length, line_ending, escape, true, v);
+    }
+    let mut col = col;
+    let mut chunks = input.chunks_exact(LANES);
+    for chunk in &mut chunks {
+        let mut lanes = [0u8; LANES];
+        lanes.copy_from_slice(chunk);
+        let encoded = add_42(u128::from_le_bytes(lanes)).to_le_bytes();
+        // checked first, so the columns of the lanes below fit in `u16`
+        let end_col = col as usize + LANES;
+        if end_col > line_length as usize
+            || (col == 0 && encoded[0] == DOT)
+            || is_whitespace_at_line_edge(encoded[0], col, line_length)
+            || is_whitespace_at_line_edge(encoded[LANES - 1], (end_col - 1) as u16, line_length)
+            || has_critical_lane(u128::from_le_bytes(encoded)) != 0
+        {
+            col = encode_into_scalar(chunk, col, line_length, line_ending, escape, false, v);
+            continue;
+        }
+        v.extend_from_slice(&encoded);
+        col = end_col as u16;
+        if col >= line_length {
+            v.extend_from_slice(line_ending.as_str().as_bytes());
+            col = 0;
+        }
+    }
+    encode_into_scalar(
+        chunks.remainder(),
+        col,
+        line_length,
+        line_ending,
+        escape,
+        false,
+        v,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::encode::{encode_into_scalar, EscapeSet, LineEnding};
+    use super::{encode_into, has_critical_lane};
+    use proptest::prelude::*;
+
+    #[test]
+    fn critical_lanes() {
+        assert_eq!(0, has_critical_lane(u128::from_le_bytes([b'a'; 16])));
+        for &critical in &[0, b'\n', b'\r', b'='] {
+            for lane in 0..16 {
+                let mut lanes = [b'a'; 16];
+                lanes[lane] = critical;
+                assert_ne!(0, has_critical_lane(u128::from_le_bytes(lanes)));
+            }
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn same_as_scalar(
+            input in proptest::collection::vec(any::<u8>(), 0..1000),
+            line_length in prop_oneof![1u16..=300, u16::MAX - 300..=u16::MAX],
+            col in any::<u16>(),
+            lf in any::<bool>(),
+            end_of_data in any::<bool>(),
+        ) {
+            let col = col % line_length;
+            let line_ending = if lf { LineEnding::Lf } else { LineEnding::CrLf };
+            let escape = EscapeSet::none();
+            let mut expected = Vec::new();
+            let expected_col = encode_into_scalar(&input, col, line_length, line_ending, escape, end_of_data, &mut expected);
+            let mut encoded = Vec::new();
+            let encoded_col = encode_into(&input, col, line_length, line_ending, escape, end_of_data, &mut encoded);
+            prop_assert_eq!(expected, encoded);
+            prop_assert_eq!(expected_col, encoded_col);
+        }
+    }
+}
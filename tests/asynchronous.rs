@@ -0,0 +1,118 @@
+#![cfg(feature = "async")]
+
+use std::env::temp_dir;
+use std::path::PathBuf;
+
+/// Returns an empty output directory for a test, removing the files of an earlier run.
+fn output_dir(name: &str) -> PathBuf {
+    let output_dir = temp_dir().join(name);
+    let _ = std::fs::remove_dir_all(&output_dir);
+    std::fs::create_dir_all(&output_dir).unwrap();
+    output_dir
+}
+
+#[tokio::test]
+async fn decode_async() {
+    let data = include_bytes!("../testdata/yenc.org/testfile.txt.yenc");
+    let expected_decoded = include_bytes!("../testdata/yenc.org/testfile.txt");
+    let output_dir = output_dir("yenc_decode_async");
+
+    let decode_options = yenc::DecodeOptions::new(&output_dir).overwrite(true);
+    let result = decode_options.decode_async(&data[..]).await.unwrap();
+    assert_eq!(result.path, output_dir.join("testfile.txt"));
+    assert!(result.checksum_ok);
+    assert_eq!(std::fs::read(&result.path).unwrap(), &expected_decoded[..]);
+}
+
+#[tokio::test]
+async fn decode_async_part() {
+    let data = include_bytes!("../testdata/multipart/testfile.txt.002.yenc");
+    let output_dir = output_dir("yenc_decode_async_part");
+
+    let decode_options = yenc::DecodeOptions::new(&output_dir).overwrite(true);
+    let result = decode_options.decode_async(&data[..]).await.unwrap();
+    assert_eq!(result.begin, Some(301));
+    assert!(result.checksum_ok);
+
+    let mut decoded = Vec::new();
+    decode_options
+        .decode_stream_to_writer(&data[..], &mut decoded)
+        .unwrap();
+    let output = std::fs::read(&result.path).unwrap();
+    assert_eq!(&output[300..584], decoded.as_slice());
+}
+
+#[tokio::test]
+async fn decode_async_to_writer() {
+    let data = include_bytes!("../testdata/yenc.org/testfile.txt.yenc");
+    let expected_decoded = include_bytes!("../testdata/yenc.org/testfile.txt");
+    let decode_options = yenc::DecodeOptions::new(temp_dir());
+    let mut decoded = Vec::new();
+    let header = decode_options
+        .decode_async_to_writer(&data[..], &mut decoded)
+        .await
+        .unwrap();
+    assert_eq!(header.name(), Some("testfile.txt"));
+    assert_eq!(decoded.as_slice(), &expected_decoded[..]);
+}
+
+#[tokio::test]
+async fn decode_async_no_yenc_data() {
+    let decode_options = yenc::DecodeOptions::new(temp_dir());
+    let result = decode_options.decode_async(&b"plain text\r\n"[..]).await;
+    assert_eq!(Err(yenc::DecodeError::NoYencData), result.map(|_| ()));
+}
+
+#[tokio::test]
+async fn decode_async_to_writer_no_yenc_data() {
+    let decode_options = yenc::DecodeOptions::new(temp_dir());
+    let mut decoded = Vec::new();
+    let result = decode_options
+        .decode_async_to_writer(&b"plain text\r\n"[..], &mut decoded)
+        .await;
+    assert_eq!(Err(yenc::DecodeError::NoYencData), result.map(|_| ()));
+}
+
+#[tokio::test]
+async fn decode_async_part_line_not_first() {
+    let data = include_bytes!("../testdata/multipart/testfile.txt.002.yenc");
+    let ypart = data.windows(7).position(|w| w == b"=ypart ").unwrap();
+    let mut input = data[..ypart].to_vec();
+    input.extend_from_slice(b"\r\n");
+    input.extend_from_slice(&data[ypart..]);
+    let output_dir = output_dir("yenc_decode_async_part_line_not_first");
+
+    let decode_options = yenc::DecodeOptions::new(&output_dir).overwrite(true);
+    let result = decode_options.decode_async(input.as_slice()).await.unwrap();
+    assert_eq!(result.begin, Some(301));
+    assert!(result.checksum_ok);
+
+    let mut decoded = Vec::new();
+    decode_options
+        .decode_stream_to_writer(&data[..], &mut decoded)
+        .unwrap();
+    let output = std::fs::read(&result.path).unwrap();
+    assert_eq!(output.len(), 584);
+    assert_eq!(&output[300..584], decoded.as_slice());
+}
+
+#[tokio::test]
+async fn decode_async_atomic_on_error() {
+    let data = include_bytes!("../testdata/yenc.org/testfile.txt.yenc");
+    let expected_decoded = include_bytes!("../testdata/yenc.org/testfile.txt");
+    let crc_start = data.windows(6).position(|w| w == b"crc32=").unwrap() + 6;
+    let mut input = data.to_vec();
+    input[crc_start..crc_start + 8].copy_from_slice(b"00000000");
+    let output_dir = output_dir("yenc_decode_async_atomic_on_error");
+
+    let decode_options = yenc::DecodeOptions::new(&output_dir)
+        .atomic(true)
+        .on_error(yenc::OnError::Delete);
+    let result = decode_options.decode_async(input.as_slice()).await;
+    assert_eq!(Err(yenc::DecodeError::InvalidChecksum), result.map(|_| ()));
+    assert_eq!(std::fs::read_dir(&output_dir).unwrap().count(), 0);
+
+    let result = decode_options.decode_async(&data[..]).await.unwrap();
+    assert_eq!(std::fs::read(&result.path).unwrap(), &expected_decoded[..]);
+    assert_eq!(std::fs::read_dir(&output_dir).unwrap().count(), 1);
+}
@@ -0,0 +1,68 @@
+use std::env::temp_dir;
+use std::fs::{create_dir_all, read, remove_dir_all};
+use std::io::Cursor;
+use std::path::PathBuf;
+
+/// Returns an empty output directory for a test, removing the files of an earlier run.
+fn output_dir(name: &str) -> PathBuf {
+    let output_dir = temp_dir().join(name);
+    let _ = remove_dir_all(&output_dir);
+    create_dir_all(&output_dir).unwrap();
+    output_dir
+}
+
+fn encode(data: &[u8], name: &str) -> Vec<u8> {
+    let mut encoded = Vec::new();
+    let encode_options = yenc::EncodeOptions::new().begin(1).end(data.len() as u64);
+    encode_options
+        .encode_stream(Cursor::new(data), &mut encoded, data.len() as u64, name)
+        .unwrap();
+    encoded
+}
+
+#[test]
+fn decode_all_blocks() {
+    let first = (0..1000).map(|c| (c % 256) as u8).collect::<Vec<u8>>();
+    let second = (0..3000).map(|c| (c % 7) as u8).collect::<Vec<u8>>();
+
+    let mut message = encode(&first, "first.bin");
+    message.extend_from_slice(b"\r\n\r\n");
+    message.extend(encode(&second, "second.bin"));
+
+    let output_dir = output_dir("yenc_decode_all_blocks");
+
+    let decode_options = yenc::DecodeOptions::new(&output_dir);
+    let results = decode_options.decode_all(message.as_slice()).unwrap();
+
+    assert_eq!(2, results.len());
+    assert_eq!(Some("first.bin"), results[0].name.as_deref());
+    assert_eq!(Some("second.bin"), results[1].name.as_deref());
+    assert!(results.iter().all(|r| r.checksum_ok));
+    assert_eq!(first, read(&results[0].path).unwrap());
+    assert_eq!(second, read(&results[1].path).unwrap());
+
+    remove_dir_all(output_dir).unwrap();
+}
+
+#[test]
+fn decode_all_trailing_block_without_footer() {
+    let first = (0..500).map(|c| (c % 256) as u8).collect::<Vec<u8>>();
+    let second = (0..100).map(|c| (c % 10) as u8).collect::<Vec<u8>>();
+
+    let mut message = encode(&first, "complete.bin");
+    let truncated = encode(&second, "truncated.bin");
+    let footer_start = truncated.windows(6).position(|w| w == b"=yend ").unwrap();
+    message.extend_from_slice(&truncated[..footer_start]);
+
+    let output_dir = output_dir("yenc_decode_all_trailing");
+
+    let decode_options = yenc::DecodeOptions::new(&output_dir);
+    let results = decode_options.decode_all(message.as_slice()).unwrap();
+
+    assert_eq!(2, results.len());
+    assert!(results[0].checksum_ok);
+    assert!(!results[1].checksum_ok);
+    assert_eq!(second, read(&results[1].path).unwrap());
+
+    remove_dir_all(output_dir).unwrap();
+}
@@ -0,0 +1,343 @@
+use std::env::temp_dir;
+use std::path::PathBuf;
+
+/// Returns an empty output directory for a test, removing the files of an earlier run.
+fn output_dir(name: &str) -> PathBuf {
+    let output_dir = temp_dir().join(name);
+    let _ = std::fs::remove_dir_all(&output_dir);
+    std::fs::create_dir_all(&output_dir).unwrap();
+    output_dir
+}
+
+fn replace(data: &[u8], from: &[u8], to: &[u8]) -> Vec<u8> {
+    let start = data.windows(from.len()).position(|w| w == from).unwrap();
+    [&data[..start], to, &data[start + from.len()..]].concat()
+}
+
+#[test]
+fn decode_part_size_from_range() {
+    let data = include_bytes!("../testdata/multipart/testfile.txt.002.yenc");
+    let expected_decoded = include_bytes!("../testdata/yenc.org/testfile.txt");
+    let mut decoded = Vec::<u8>::new();
+    let decode_options = yenc::DecodeOptions::new(temp_dir());
+    let header = decode_options
+        .decode_stream_to_writer(&data[..], &mut decoded)
+        .unwrap();
+    assert_eq!(decoded.as_slice(), &expected_decoded[300..]);
+    assert_eq!(Some(2), header.part());
+    assert_eq!(Some(301), header.begin());
+    assert_eq!(Some(584), header.end());
+    assert_eq!(Some(284), header.size());
+}
+
+#[test]
+fn decode_part_and_total_from_footer() {
+    let data = include_bytes!("../testdata/multipart/testfile.txt.002.yenc");
+    let input = replace(data, b"=ybegin part=2 ", b"=ybegin ");
+    let input = replace(&input, b"part=2 pcrc32", b"part=2 total=2 pcrc32");
+    let mut decoded = Vec::<u8>::new();
+    let header = yenc::DecodeOptions::new(temp_dir())
+        .decode_stream_to_writer(input.as_slice(), &mut decoded)
+        .unwrap();
+    assert_eq!(Some(2), header.part());
+    assert_eq!(Some(2), header.total());
+
+    let input = replace(data, b"part=2 pcrc32", b"part=3 total=2 pcrc32");
+    let header = yenc::DecodeOptions::new(temp_dir())
+        .decode_stream_to_writer(input.as_slice(), &mut decoded)
+        .unwrap();
+    assert_eq!(Some(2), header.part());
+    assert_eq!(Some(2), header.total());
+}
+
+#[test]
+fn decode_part_and_total_from_ypart() {
+    let data = include_bytes!("../testdata/multipart/testfile.txt.002.yenc");
+    let input = replace(data, b"=ybegin part=2 ", b"=ybegin ");
+    let input = replace(&input, b"=ypart begin", b"=ypart part=2 total=2 begin");
+    let mut decoded = Vec::<u8>::new();
+    let header = yenc::DecodeOptions::new(temp_dir())
+        .decode_stream_to_writer(input.as_slice(), &mut decoded)
+        .unwrap();
+    assert_eq!(Some(2), header.part());
+    assert_eq!(Some(2), header.total());
+    assert_eq!(Some(301), header.begin());
+    assert_eq!(Some(584), header.end());
+
+    let input = replace(data, b"=ypart begin", b"=ypart part=3 total=3 begin");
+    let header = yenc::DecodeOptions::new(temp_dir())
+        .decode_stream_to_writer(input.as_slice(), &mut decoded)
+        .unwrap();
+    assert_eq!(Some(2), header.part());
+    assert_eq!(Some(3), header.total());
+}
+
+#[test]
+fn decode_parts_into_open_file() {
+    let data = include_bytes!("../testdata/yenc.org/testfile.txt");
+    let part1 = yenc::EncodeOptions::new()
+        .parts(2)
+        .part(1)
+        .begin(1)
+        .end(300)
+        .encode_bytes(data, "testfile.txt")
+        .unwrap();
+    let part2 = include_bytes!("../testdata/multipart/testfile.txt.002.yenc");
+
+    let path = temp_dir().join("yenc_decode_parts_into_open_file.txt");
+    let mut file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&path)
+        .unwrap();
+    let decode_options = yenc::DecodeOptions::new(temp_dir());
+    let header = decode_options
+        .decode_stream_into_file(&part2[..], &mut file)
+        .unwrap();
+    assert_eq!(Some(2), header.part());
+    decode_options
+        .decode_stream_into_file(part1.as_slice(), &mut file)
+        .unwrap();
+    assert_eq!(
+        Err(yenc::DecodeError::NoYencData),
+        decode_options
+            .decode_stream_into_file(&b"no yEnc data\r\n"[..], &mut file)
+            .map(|_| ())
+    );
+    drop(file);
+    assert_eq!(&data[..], std::fs::read(&path).unwrap().as_slice());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn decode_header_only() {
+    let data = include_bytes!("../testdata/multipart/testfile.txt.002.yenc");
+    let header = yenc::DecodeOptions::new(temp_dir())
+        .decode_header_only(&data[..])
+        .unwrap();
+    assert_eq!(Some("testfile.txt"), header.name());
+    assert_eq!(Some(2), header.part());
+    assert_eq!(Some(301), header.begin());
+    assert_eq!(Some(584), header.end());
+    assert_eq!(None, header.pcrc32());
+}
+
+#[test]
+fn decode_part_incomplete() {
+    let data = include_bytes!("../testdata/multipart/testfile.txt.002.yenc");
+    let input = replace(data, b"end=584", b"end=600");
+    let mut decoded = Vec::<u8>::new();
+    let decode_options = yenc::DecodeOptions::new(temp_dir());
+    match decode_options.decode_stream_to_writer(input.as_slice(), &mut decoded) {
+        Err(yenc::DecodeError::IncompleteData {
+            expected_size,
+            actual_size,
+        }) => {
+            assert_eq!(300, expected_size);
+            assert_eq!(284, actual_size);
+        }
+        other => panic!("unexpected result {:?}", other),
+    }
+}
+
+#[test]
+fn decode_part_truncated_without_footer() {
+    let data = include_bytes!("../testdata/multipart/testfile.txt.002.yenc");
+    let first_data_line_end = data
+        .iter()
+        .enumerate()
+        .filter(|(_, &c)| c == b'\n')
+        .nth(2)
+        .unwrap()
+        .0;
+    let input = &data[..=first_data_line_end];
+
+    let mut decoded = Vec::<u8>::new();
+    let decode_options = yenc::DecodeOptions::new(temp_dir());
+    match decode_options.decode_stream_to_writer(input, &mut decoded) {
+        Err(yenc::DecodeError::IncompleteData {
+            expected_size,
+            actual_size,
+        }) => {
+            assert_eq!(284, expected_size);
+            assert_eq!(decoded.len(), actual_size);
+            assert!(actual_size < expected_size);
+        }
+        other => panic!("unexpected result {:?}", other),
+    }
+}
+
+#[test]
+fn decode_part_complete_without_footer() {
+    let data = include_bytes!("../testdata/multipart/testfile.txt.002.yenc");
+    let footer_start = data.windows(6).position(|w| w == b"=yend ").unwrap();
+    let input = &data[..footer_start];
+
+    let mut decoded = Vec::<u8>::new();
+    let decode_options = yenc::DecodeOptions::new(temp_dir());
+    assert!(decode_options
+        .decode_stream_to_writer(input, &mut decoded)
+        .is_ok());
+
+    let decode_options = yenc::DecodeOptions::new(temp_dir()).strict(true);
+    assert_eq!(
+        Err(yenc::DecodeError::MissingFooter),
+        decode_options.decode_stream_to_writer(input, &mut decoded)
+    );
+}
+
+#[test]
+fn decode_parts_into_overridden_file() {
+    let data = include_bytes!("../testdata/yenc.org/testfile.txt");
+    let part2 = include_bytes!("../testdata/multipart/testfile.txt.002.yenc");
+    let mut part1 = Vec::new();
+    yenc::EncodeOptions::new()
+        .parts(2)
+        .part(1)
+        .begin(1)
+        .end(300)
+        .encode_stream(
+            std::io::Cursor::new(&data[..]),
+            &mut part1,
+            data.len() as u64,
+            "testfile.txt",
+        )
+        .unwrap();
+
+    let output_dir = output_dir("yenc_override_filename");
+    let output_path = output_dir.join("reassembled.txt");
+    let _ = std::fs::remove_file(&output_path);
+
+    let decode_options = yenc::DecodeOptions::new(&output_dir).override_filename("reassembled.txt");
+    let result = decode_options.decode_stream(&part2[..]).unwrap();
+    assert_eq!(result.path, output_path);
+    let result = decode_options.decode_stream(part1.as_slice()).unwrap();
+    assert_eq!(result.path, output_path);
+    assert_eq!(std::fs::read(&output_path).unwrap(), &data[..]);
+}
+
+#[test]
+fn decode_segments_append() {
+    let data = include_bytes!("../testdata/yenc.org/testfile.txt");
+    let mut segments = Vec::new();
+    for chunk in data.chunks(300) {
+        let mut segment = Vec::new();
+        yenc::EncodeOptions::new()
+            .encode_stream(
+                std::io::Cursor::new(chunk),
+                &mut segment,
+                chunk.len() as u64,
+                "appended.txt",
+            )
+            .unwrap();
+        segments.push(segment);
+    }
+
+    let output_dir = output_dir("yenc_append");
+    let output_path = output_dir.join("appended.txt");
+    let _ = std::fs::remove_file(&output_path);
+
+    let decode_options = yenc::DecodeOptions::new(&output_dir).append(true);
+    for segment in &segments {
+        decode_options.decode_stream(segment.as_slice()).unwrap();
+    }
+    assert_eq!(std::fs::read(&output_path).unwrap(), &data[..]);
+    assert!(decode_options.verify_crc(&output_path, 0xded2_9f4f).is_ok());
+    assert_eq!(
+        Err(yenc::DecodeError::InvalidChecksum),
+        decode_options.verify_crc(&output_path, 0)
+    );
+}
+
+#[test]
+fn decode_part_verbose_report() {
+    let data = include_bytes!("../testdata/multipart/testfile.txt.002.yenc");
+    let input = replace(data, b"end=584", b"end=600");
+    let input = replace(&input, b"pcrc32=b46f5818", b"pcrc32=00000000");
+
+    let output_dir = output_dir("yenc_verbose_report");
+
+    let decode_options = yenc::DecodeOptions::new(&output_dir).overwrite(true);
+    let report = decode_options
+        .decode_stream_verbose(input.as_slice())
+        .unwrap();
+    assert_eq!(
+        yenc::ValidationReport {
+            bytes_written: 284,
+            size_ok: Some(false),
+            part_crc_ok: Some(false),
+            file_crc_ok: None,
+            footer_present: true,
+        },
+        report
+    );
+
+    let report = decode_options.decode_stream_verbose(&data[..]).unwrap();
+    assert_eq!(Some(true), report.size_ok);
+    assert_eq!(Some(true), report.part_crc_ok);
+
+    std::fs::remove_dir_all(output_dir).unwrap();
+}
+
+#[test]
+fn decode_verbose_report_atomic() {
+    let data = include_bytes!("../testdata/multipart/testfile.txt.002.yenc");
+    let input = replace(data, b"pcrc32=b46f5818", b"pcrc32=00000000");
+    let output_dir = output_dir("yenc_verbose_report_atomic");
+    let output_file = output_dir.join("testfile.txt");
+    let temp_file = output_dir.join("testfile.txt.part002.tmp");
+
+    let decode_options = yenc::DecodeOptions::new(&output_dir).atomic(true);
+    let report = decode_options
+        .decode_stream_verbose(input.as_slice())
+        .unwrap();
+    assert_eq!(Some(false), report.part_crc_ok);
+    assert!(!output_file.exists());
+    assert!(temp_file.exists());
+
+    let report = decode_options
+        .clone()
+        .on_error(yenc::OnError::Delete)
+        .decode_stream_verbose(input.as_slice())
+        .unwrap();
+    assert_eq!(Some(false), report.part_crc_ok);
+    assert!(!output_file.exists());
+    assert!(!temp_file.exists());
+
+    let report = decode_options.decode_stream_verbose(&data[..]).unwrap();
+    assert_eq!(Some(true), report.part_crc_ok);
+    assert!(output_file.exists());
+    assert!(!temp_file.exists());
+
+    std::fs::remove_dir_all(output_dir).unwrap();
+}
+
+#[test]
+fn decode_inverted_part_range() {
+    let data = include_bytes!("../testdata/multipart/testfile.txt.002.yenc");
+    let input = replace(data, b"begin=301 end=584", b"begin=5 end=2");
+    match yenc::decode_message(&input) {
+        Err(yenc::DecodeError::At { source, .. }) => {
+            assert!(matches!(*source, yenc::DecodeError::InvalidHeader { .. }))
+        }
+        result => panic!("unexpected result {:?}", result),
+    }
+}
+
+#[test]
+fn decode_part_begin_zero() {
+    let data = include_bytes!("../testdata/multipart/testfile.txt.002.yenc");
+    let input = replace(data, b"begin=301 end=584", b"begin=0 end=283");
+    let output_dir = output_dir("yenc_decode_part_begin_zero");
+    match yenc::DecodeOptions::new(&output_dir)
+        .overwrite(true)
+        .decode_stream(input.as_slice())
+    {
+        Err(yenc::DecodeError::At { source, .. }) => {
+            assert!(matches!(*source, yenc::DecodeError::InvalidHeader { .. }))
+        }
+        result => panic!("unexpected result {:?}", result),
+    }
+}
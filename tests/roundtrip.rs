@@ -1,6 +1,7 @@
+use proptest::prelude::*;
 use rand::random;
 use std::env::temp_dir;
-use std::fs::{create_dir, remove_dir, remove_file, File};
+use std::fs::{create_dir, remove_dir_all, remove_file, File};
 use std::io::{Read, Result, Write};
 use std::path::Path;
 use std::sync::Mutex;
@@ -9,75 +10,52 @@ lazy_static::lazy_static! {
     static ref M: Mutex<u8> = Mutex::new(0);
 }
 
-fn encode(input_filename: &str) {
-    let parts = 1;
-
+fn encode(input_filename: &str, parts: u32) {
     println!("{}", input_filename);
-    let input_file = File::open(input_filename).expect("Cannot open file");
-
-    let path = Path::new(&input_filename);
-    let total_size = input_file.metadata().unwrap().len();
-    let part_size: u64 = total_size / u64::from(parts);
 
-    for part in 1..=parts {
+    let encode_options = yenc::EncodeOptions::new();
+    let result = encode_options.encode_file_multipart(input_filename, parts, |part| {
         let output_filename = format!("{}.{:03}", input_filename, part);
-        let mut output_file = File::create(&output_filename).expect("Cannot create file");
         println!("{}", output_filename);
-
-        let begin = (u64::from(part) - 1) * part_size + 1;
-        let end = if begin + part_size < total_size {
-            begin + part_size - 1
-        } else {
-            total_size
-        };
-
-        let encode_options = yenc::EncodeOptions::new()
-            .parts(parts)
-            .part(part)
-            .begin(begin)
-            .end(end);
-
-        match encode_options.encode_file(path, &mut output_file) {
-            Err(err) => {
-                println!(
-                    "Error yEncoding {} to {}: {}",
-                    input_filename, output_filename, err
-                );
-            }
-            Ok(_) => {
-                println!(
-                    "Successfully yEncoded {} to {}",
-                    input_filename, output_filename
-                );
-            }
-        };
-    }
+        File::create(&output_filename)
+    });
+    match result {
+        Err(err) => {
+            println!("Error yEncoding {}: {}", input_filename, err);
+        }
+        Ok(part_infos) => {
+            assert_eq!(parts as usize, part_infos.len());
+            println!("Successfully yEncoded {}", input_filename);
+        }
+    };
 }
 
 fn decode(input_filename: &str, output_directory: &str) -> u32 {
-    let decode_options = yenc::DecodeOptions::new(output_directory);
+    let decode_options = yenc::DecodeOptions::new(output_directory).overwrite(true);
     match decode_options.decode_file(input_filename) {
         Err(err) => {
             println!("Error yEnc decoding {}: {}", input_filename, err);
             1
         }
-        Ok(output_filename) => {
+        Ok(result) => {
             println!(
                 "Successfully yEnc decoded {} to {}",
                 input_filename,
-                output_filename.display()
+                result.path.display()
             );
             0
         }
     }
 }
 
-fn encode_decode_are_equal(data: &[u8], filename: &str) -> Result<bool> {
+fn encode_decode_are_equal(data: &[u8], filename: &str, parts: u32) -> Result<bool> {
     // synchronize, to prevent test cases run in parallel and mess up directories
     let _x = M.lock().unwrap();
 
-    // create temp dir
-    let tmpdir = temp_dir();
+    // create an empty temp dir for the test case, removing the files of an earlier run
+    let tmpdir = temp_dir().join(format!("yenc_roundtrip_{}", filename));
+    let _ = remove_dir_all(&tmpdir);
+    create_dir(&tmpdir)?;
     println!("{}", tmpdir.display());
 
     // created 'decoded' dir in temp dir
@@ -86,22 +64,20 @@ fn encode_decode_are_equal(data: &[u8], filename: &str) -> Result<bool> {
     create_dir(decoded_dir.clone())?;
 
     // dump data to file
-    let mut filepath = tmpdir;
+    let mut filepath = tmpdir.clone();
     filepath.push(filename);
     let mut f = File::create(&filepath)?;
     f.write_all(data).unwrap();
 
     // encode file
-    encode(filepath.to_str().unwrap());
-
-    let mut decoded_file = decoded_dir.clone();
-    decoded_file.push(filename);
+    encode(filepath.to_str().unwrap(), parts);
 
-    // decode file
-    decode(
-        &(filepath.to_str().unwrap().to_owned() + ".001"),
-        decoded_dir.to_str().unwrap(),
-    );
+    // decode all parts
+    for part in 1..=parts {
+        let part_filename = format!("{}.{:03}", filepath.to_str().unwrap(), part);
+        decode(&part_filename, decoded_dir.to_str().unwrap());
+        remove_file(part_filename)?;
+    }
 
     // check that files are identical
     let mut decoded_file = decoded_dir.clone();
@@ -109,9 +85,7 @@ fn encode_decode_are_equal(data: &[u8], filename: &str) -> Result<bool> {
     let result = identical(filepath.clone(), decoded_file.clone());
 
     //clean up
-    remove_file(filepath)?;
-    remove_file(decoded_file)?;
-    remove_dir(decoded_dir)?;
+    remove_dir_all(tmpdir)?;
 
     Ok(result)
 }
@@ -130,7 +104,7 @@ fn test_ascii() {
         .map(|c| (c & 0x7f) as u8)
         .collect::<Vec<u8>>();
 
-    assert!(encode_decode_are_equal(&data, "ascii").unwrap());
+    assert!(encode_decode_are_equal(&data, "ascii", 1).unwrap());
 }
 
 #[test]
@@ -139,12 +113,74 @@ fn test_binary() {
         .map(|c| (c & 0xff) as u8)
         .collect::<Vec<u8>>();
 
-    assert!(encode_decode_are_equal(&data, "binary").unwrap());
+    assert!(encode_decode_are_equal(&data, "binary", 1).unwrap());
 }
 
 #[test]
 fn test_random() {
     let data = (0..10_000_000).map(|_| random::<u8>()).collect::<Vec<u8>>();
 
-    assert!(encode_decode_are_equal(&data, "random").unwrap());
+    assert!(encode_decode_are_equal(&data, "random", 1).unwrap());
+}
+
+#[test]
+fn test_multipart() {
+    let data = (0..1_000_003).map(|_| random::<u8>()).collect::<Vec<u8>>();
+
+    assert!(encode_decode_are_equal(&data, "multipart", 7).unwrap());
+}
+
+proptest! {
+    #[test]
+    fn chained_encode_buffer_decode_buffer(
+        data in proptest::collection::vec(any::<u8>(), 0..2000),
+        line_length in 1u16..300,
+        mut splits in proptest::collection::vec(0usize..2000, 0..10),
+    ) {
+        splits.push(data.len());
+        splits.iter_mut().for_each(|split| *split = (*split).min(data.len()));
+        splits.sort_unstable();
+
+        let mut encoded = Vec::new();
+        let mut state = yenc::EncodeState::start();
+        let mut checksum = yenc::Crc32::new();
+        let mut start = 0;
+        for &end in &splits {
+            state = yenc::encode_buffer(&data[start..end], state, line_length, &mut encoded).unwrap();
+            checksum.update_with_slice(&data[start..end]);
+            start = end;
+        }
+        for line in encoded.split(|&b| b == b'\n') {
+            // an escaped character can end one character after the line length
+            prop_assert!(line.len() <= line_length as usize + 2);
+        }
+
+        let decoded = yenc::decode_buffer(&encoded).unwrap();
+        prop_assert_eq!(&data, &decoded);
+        prop_assert_eq!(checksum.crc(), yenc::crc32(&decoded));
+    }
+}
+
+#[test]
+fn extra_header_fields_roundtrip() {
+    let data = (0..1000u16).map(|c| c as u8).collect::<Vec<u8>>();
+    let encoded = yenc::EncodeOptions::new()
+        .extra_header_field("desc", "holiday_photos")
+        .extra_header_field("poster", "a=b")
+        .encode_bytes(&data, "extra.bin")
+        .unwrap();
+
+    let decode_options = yenc::DecodeOptions::new(temp_dir()).ignore_unknown_keywords(true);
+    let header = decode_options
+        .decode_header_only(encoded.as_slice())
+        .unwrap();
+    assert_eq!(Some("extra.bin"), header.name());
+    assert_eq!(Some(1000), header.size());
+
+    let mut decoded = Vec::new();
+    let header = decode_options
+        .decode_stream_to_writer(encoded.as_slice(), &mut decoded)
+        .unwrap();
+    assert_eq!(Some("extra.bin"), header.name());
+    assert_eq!(data, decoded);
 }
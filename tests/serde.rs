@@ -0,0 +1,25 @@
+#![cfg(feature = "serde")]
+
+#[test]
+fn header_to_json() {
+    let header =
+        yenc::parse_header(b"=ybegin part=1 total=2 line=128 size=1000 name=a.bin\r\n").unwrap();
+    let json = serde_json::to_string(&header).unwrap();
+    assert_eq!(
+        json,
+        r#"{"name":"a.bin","line_length":128,"size":1000,"crc32":null,"pcrc32":null,"part":1,"total":2,"begin":null,"end":null}"#
+    );
+    let parsed: yenc::Header = serde_json::from_str(&json).unwrap();
+    assert_eq!(json, serde_json::to_string(&parsed).unwrap());
+}
+
+#[test]
+fn header_crc32_as_hex() {
+    let header =
+        yenc::parse_header(b"=yend size=584 part=1 pcrc32=0b46f581 crc32=ded29f4f\r\n").unwrap();
+    let json = serde_json::to_value(&header).unwrap();
+    assert_eq!(json["crc32"], "ded29f4f");
+    assert_eq!(json["pcrc32"], "0b46f581");
+    let parsed: yenc::Header = serde_json::from_value(json).unwrap();
+    assert_eq!(parsed.pcrc32(), Some(0x0b46_f581));
+}
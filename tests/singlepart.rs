@@ -1,6 +1,15 @@
 use std::env::temp_dir;
 use std::fs::File;
 use std::io::Read;
+use std::path::PathBuf;
+
+/// Returns an empty output directory for a test, removing the files of an earlier run.
+fn output_dir(name: &str) -> PathBuf {
+    let output_dir = temp_dir().join(name);
+    let _ = std::fs::remove_dir_all(&output_dir);
+    std::fs::create_dir_all(&output_dir).unwrap();
+    output_dir
+}
 
 #[test]
 fn encode() {
@@ -23,16 +32,219 @@ fn decode() {
     let expected_decoded = include_bytes!("../testdata/yenc.org/testfile.txt");
     let mut decoded = Vec::<u8>::new();
     let mut c = std::io::Cursor::new(&data[..]);
-    let tmpdir = temp_dir();
+    let tmpdir = output_dir("yenc_decode");
     let mut tmpfile = tmpdir.clone();
     tmpfile.push("testfile.txt");
-    let decode_options = yenc::DecodeOptions::new(tmpdir);
-    decode_options.decode_stream(&mut c).unwrap();
+    let decode_options = yenc::DecodeOptions::new(tmpdir).overwrite(true);
+    let result = decode_options.decode_stream(&mut c).unwrap();
     File::open(&tmpfile)
         .unwrap()
         .read_to_end(&mut decoded)
         .unwrap();
     assert_eq!(decoded.as_slice(), &expected_decoded[..]);
+    assert_eq!(result.path, tmpfile);
+    assert_eq!(result.name.as_deref(), Some("testfile.txt"));
+    assert_eq!(result.size, Some(584));
+    assert_eq!(result.crc32, Some(0xded2_9f4f));
+    assert!(result.checksum_ok);
+}
+
+#[test]
+fn decode_file_path() {
+    let expected_decoded = include_bytes!("../testdata/yenc.org/testfile.txt");
+    let output_dir = output_dir("yenc_decode_file_path");
+    let input_path = std::path::Path::new("testdata/yenc.org/testfile.txt.yenc");
+    let decode_options = yenc::DecodeOptions::new(&output_dir).overwrite(true);
+    let result = decode_options.decode_file(input_path).unwrap();
+    assert_eq!(result.path, output_dir.join("testfile.txt"));
+    assert_eq!(std::fs::read(&result.path).unwrap(), &expected_decoded[..]);
+}
+
+#[test]
+fn decode_bufread() {
+    let data = include_bytes!("../testdata/yenc.org/testfile.txt.yenc");
+    let expected_decoded = include_bytes!("../testdata/yenc.org/testfile.txt");
+    let output_dir = output_dir("yenc_decode_bufread");
+    let decode_options = yenc::DecodeOptions::new(&output_dir).overwrite(true);
+    let result = decode_options
+        .decode_bufread(std::io::BufReader::with_capacity(16, &data[..]))
+        .unwrap();
+    assert!(result.checksum_ok);
+    assert_eq!(
+        std::fs::read(&result.path).unwrap().as_slice(),
+        &expected_decoded[..]
+    );
+}
+
+#[test]
+fn decode_to_dir() {
+    let data = include_bytes!("../testdata/yenc.org/testfile.txt.yenc");
+    let expected_decoded = include_bytes!("../testdata/yenc.org/testfile.txt");
+    let mut output_dir = output_dir("yenc_decode_to_dir");
+    let decode_options = yenc::DecodeOptions::new("/nonexistent").overwrite(true);
+    let result = decode_options
+        .decode_stream_to_dir(&data[..], &output_dir)
+        .unwrap();
+    output_dir.push("testfile.txt");
+    assert_eq!(result.path, output_dir);
+    assert_eq!(
+        std::fs::read(&output_dir).unwrap().as_slice(),
+        &expected_decoded[..]
+    );
+}
+
+#[test]
+fn decode_on_error() {
+    let data = include_bytes!("../testdata/yenc.org/testfile.txt.yenc");
+    let crc_start = data.windows(6).position(|w| w == b"crc32=").unwrap() + 6;
+    let mut input = data.to_vec();
+    input[crc_start..crc_start + 8].copy_from_slice(b"00000000");
+
+    let output_dir = output_dir("yenc_decode_on_error");
+    let mut output_file = output_dir.clone();
+    output_file.push("testfile.txt");
+
+    let decode_options = yenc::DecodeOptions::new(&output_dir).on_error(yenc::OnError::Delete);
+    assert_eq!(
+        Err(yenc::DecodeError::InvalidChecksum),
+        decode_options.decode_stream(input.as_slice()).map(|_| ())
+    );
+    assert!(!output_file.exists());
+
+    let decode_options = yenc::DecodeOptions::new(&output_dir);
+    assert_eq!(
+        Err(yenc::DecodeError::InvalidChecksum),
+        decode_options.decode_stream(input.as_slice()).map(|_| ())
+    );
+    assert!(output_file.exists());
+
+    // an existing file is not removed
+    let decode_options = yenc::DecodeOptions::new(&output_dir)
+        .overwrite(true)
+        .on_error(yenc::OnError::Delete);
+    assert_eq!(
+        Err(yenc::DecodeError::InvalidChecksum),
+        decode_options.decode_stream(input.as_slice()).map(|_| ())
+    );
+    assert!(output_file.exists());
+}
+
+#[test]
+fn decode_atomic() {
+    let data = include_bytes!("../testdata/yenc.org/testfile.txt.yenc");
+    let expected_decoded = include_bytes!("../testdata/yenc.org/testfile.txt");
+    let crc_start = data.windows(6).position(|w| w == b"crc32=").unwrap() + 6;
+    let mut input = data.to_vec();
+    input[crc_start..crc_start + 8].copy_from_slice(b"00000000");
+
+    let output_dir = output_dir("yenc_decode_atomic");
+    let output_file = output_dir.join("testfile.txt");
+    let temp_file = output_dir.join("testfile.txt.tmp");
+
+    let decode_options = yenc::DecodeOptions::new(&output_dir).atomic(true);
+    assert_eq!(
+        Err(yenc::DecodeError::InvalidChecksum),
+        decode_options.decode_stream(input.as_slice()).map(|_| ())
+    );
+    assert!(!output_file.exists());
+    assert!(temp_file.exists());
+
+    let result = decode_options.decode_stream(&data[..]).unwrap();
+    assert_eq!(result.path, output_file);
+    assert!(!temp_file.exists());
+    assert_eq!(
+        std::fs::read(&output_file).unwrap().as_slice(),
+        &expected_decoded[..]
+    );
+}
+
+#[test]
+fn decode_empty_filename() {
+    let data = include_bytes!("../testdata/yenc.org/testfile.txt.yenc");
+    let name_start = data.windows(5).position(|w| w == b"name=").unwrap() + 5;
+    let name_end = name_start + data[name_start..].iter().position(|&b| b == b'\r').unwrap();
+    let input = [&data[..name_start], &data[name_end..]].concat();
+
+    let output_dir = output_dir("yenc_decode_empty_filename");
+    let decode_options = yenc::DecodeOptions::new(&output_dir);
+    assert_eq!(
+        Err(yenc::DecodeError::MissingFilename),
+        decode_options.decode_stream(input.as_slice())
+    );
+    let result = decode_options
+        .override_filename("testfile.txt")
+        .overwrite(true)
+        .decode_stream(input.as_slice())
+        .unwrap();
+    assert_eq!(output_dir.join("testfile.txt"), result.path);
+}
+
+#[test]
+fn decode_no_yenc_data() {
+    let input: &[u8] = b"Hello,\r\nthis is plain text.\r\n";
+    let decode_options = yenc::DecodeOptions::new(temp_dir());
+    type EntryPoint<'a> = Box<dyn Fn() -> Result<(), yenc::DecodeError> + 'a>;
+    let entry_points: Vec<(&str, EntryPoint)> = vec![
+        (
+            "decode_stream",
+            Box::new(|| decode_options.decode_stream(input).map(|_| ())),
+        ),
+        (
+            "decode_bufread",
+            Box::new(|| decode_options.decode_bufread(input).map(|_| ())),
+        ),
+        (
+            "decode_stream_to_writer",
+            Box::new(|| {
+                let mut decoded = Vec::new();
+                let result = decode_options.decode_stream_to_writer(input, &mut decoded);
+                assert!(decoded.is_empty());
+                result.map(|_| ())
+            }),
+        ),
+        (
+            "decode_message",
+            Box::new(|| yenc::decode_message(input).map(|_| ())),
+        ),
+        (
+            "decode_header_only",
+            Box::new(|| decode_options.decode_header_only(input).map(|_| ())),
+        ),
+        (
+            "decode_stream_verbose",
+            Box::new(|| decode_options.decode_stream_verbose(input).map(|_| ())),
+        ),
+        (
+            "decode_all",
+            Box::new(|| decode_options.decode_all(input).map(|_| ())),
+        ),
+        (
+            "Decoder",
+            Box::new(|| {
+                let mut decoder = yenc::Decoder::new(input);
+                let result = decoder.next().unwrap();
+                assert!(decoder.next().is_none());
+                result.map(|_| ())
+            }),
+        ),
+        (
+            "DecodeReader",
+            Box::new(|| {
+                let mut reader = yenc::DecodeReader::new(input);
+                let err = std::io::copy(&mut reader, &mut std::io::sink()).unwrap_err();
+                assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+                Err(*err.into_inner().unwrap().downcast().unwrap())
+            }),
+        ),
+    ];
+    for (name, entry_point) in entry_points {
+        assert_eq!(
+            Err(yenc::DecodeError::NoYencData),
+            entry_point(),
+            "{}",
+            name
+        );
+    }
 }
 
 #[test]
@@ -41,15 +253,505 @@ fn decode_no_checksums() {
     let expected_decoded = include_bytes!("../testdata/yenc.org/testfile.txt");
     let mut decoded = Vec::<u8>::new();
     let mut c = std::io::Cursor::new(&data[..]);
-    let tmpdir = temp_dir();
+    let tmpdir = output_dir("yenc_decode_no_checksums");
     let mut tmpfile = tmpdir.clone();
     tmpfile.push("testfile.txt");
-    let decode_options = yenc::DecodeOptions::new(tmpdir);
-    decode_options.decode_stream(&mut c).unwrap();
+    let decode_options = yenc::DecodeOptions::new(tmpdir).overwrite(true);
+    let result = decode_options.decode_stream(&mut c).unwrap();
     File::open(&tmpfile)
         .unwrap()
         .read_to_end(&mut decoded)
         .unwrap();
 
     assert_eq!(decoded.as_slice(), &expected_decoded[..]);
+    assert!(!result.checksum_ok);
+}
+
+#[test]
+fn decode_to_writer() {
+    let data = include_bytes!("../testdata/yenc.org/testfile.txt.yenc");
+    let expected_decoded = include_bytes!("../testdata/yenc.org/testfile.txt");
+    let mut decoded = Vec::<u8>::new();
+    let decode_options = yenc::DecodeOptions::new(temp_dir());
+    let header = decode_options
+        .decode_stream_to_writer(&data[..], &mut decoded)
+        .unwrap();
+    assert_eq!(decoded.as_slice(), &expected_decoded[..]);
+    assert_eq!(header.name(), Some("testfile.txt"));
+    assert_eq!(header.crc32(), Some(0xded2_9f4f));
+}
+
+#[test]
+fn decode_tolerant_prefix() {
+    let data = include_bytes!("../testdata/yenc.org/testfile.txt.yenc");
+    let expected_decoded = include_bytes!("../testdata/yenc.org/testfile.txt");
+    let begin = data.windows(8).position(|w| w == b"=ybegin ").unwrap();
+    let mut input = data[..begin].to_vec();
+    input.extend_from_slice(b"\xef\xbb\xbf> ");
+    input.extend_from_slice(&data[begin..]);
+
+    let mut decoded = Vec::<u8>::new();
+    assert_eq!(
+        Err(yenc::DecodeError::NoYencData),
+        yenc::DecodeOptions::new(temp_dir())
+            .decode_stream_to_writer(input.as_slice(), &mut decoded)
+            .map(|_| ())
+    );
+    assert!(decoded.is_empty());
+
+    let mut decoded = Vec::<u8>::new();
+    let header = yenc::DecodeOptions::new(temp_dir())
+        .tolerant_prefix(true)
+        .decode_stream_to_writer(input.as_slice(), &mut decoded)
+        .unwrap();
+    assert_eq!(decoded.as_slice(), &expected_decoded[..]);
+    assert_eq!(header.name(), Some("testfile.txt"));
+}
+
+#[test]
+fn decode_nntp_dot_termination() {
+    let data = include_bytes!("../testdata/yenc.org/testfile.txt.yenc");
+    let expected_decoded = include_bytes!("../testdata/yenc.org/testfile.txt");
+    let footer = data.windows(6).position(|w| w == b"=yend ").unwrap();
+    let mut input = data[..footer].to_vec();
+    input.extend_from_slice(b".\r\n");
+    input.extend_from_slice(&data[footer..]);
+
+    let mut decoded = Vec::<u8>::new();
+    assert!(yenc::DecodeOptions::new(temp_dir())
+        .decode_stream_to_writer(input.as_slice(), &mut decoded)
+        .is_err());
+
+    let mut decoded = Vec::<u8>::new();
+    let header = yenc::DecodeOptions::new(temp_dir())
+        .nntp_dot_termination(true)
+        .decode_stream_to_writer(input.as_slice(), &mut decoded)
+        .unwrap();
+    assert_eq!(decoded.as_slice(), &expected_decoded[..]);
+    assert_eq!(header.crc32(), Some(0xded2_9f4f));
+}
+
+#[test]
+fn decode_max_output_bytes() {
+    let data = include_bytes!("../testdata/yenc.org/testfile.txt.yenc");
+    let expected_decoded = include_bytes!("../testdata/yenc.org/testfile.txt");
+
+    let mut decoded = Vec::<u8>::new();
+    assert_eq!(
+        Err(yenc::DecodeError::OutputTooLarge { limit: 500 }),
+        yenc::DecodeOptions::new(temp_dir())
+            .max_output_bytes(500)
+            .decode_stream_to_writer(&data[..], &mut decoded)
+            .map(|_| ())
+    );
+    assert!(decoded.len() <= 500);
+
+    let mut decoded = Vec::<u8>::new();
+    yenc::DecodeOptions::new(temp_dir())
+        .max_output_bytes(expected_decoded.len())
+        .decode_stream_to_writer(&data[..], &mut decoded)
+        .unwrap();
+    assert_eq!(decoded.as_slice(), &expected_decoded[..]);
+}
+
+#[test]
+fn decode_max_output_bytes_file() {
+    let output_dir = output_dir("yenc_decode_max_output_bytes_file");
+    let mut input = b"=ybegin line=128 size=999999999999 name=big.bin\r\n".to_vec();
+    yenc::encode_buffer(b"abc", yenc::EncodeState::start(), 128, &mut input).unwrap();
+    input.extend_from_slice(b"\r\n=yend size=3\r\n");
+
+    assert_eq!(
+        Err(yenc::DecodeError::OutputTooLarge { limit: 2 }),
+        yenc::DecodeOptions::new(&output_dir)
+            .max_output_bytes(2)
+            .decode_stream(input.as_slice())
+            .map(|_| ())
+    );
+    let output_file = output_dir.join("big.bin");
+    assert!(std::fs::metadata(&output_file).unwrap().len() <= 2);
+    std::fs::remove_dir_all(&output_dir).unwrap();
+}
+
+#[test]
+fn decode_message() {
+    let data = include_bytes!("../testdata/yenc.org/testfile.txt.yenc");
+    let expected_decoded = include_bytes!("../testdata/yenc.org/testfile.txt");
+    let (decoded, header) = yenc::decode_message(data).unwrap();
+    assert_eq!(decoded.as_slice(), &expected_decoded[..]);
+    assert_eq!(header.name(), Some("testfile.txt"));
+    assert_eq!(header.crc32(), Some(0xded2_9f4f));
+
+    let mut input = data.to_vec();
+    let crc_start = data.windows(6).position(|w| w == b"crc32=").unwrap() + 6;
+    input[crc_start..crc_start + 8].copy_from_slice(b"00000000");
+    assert_eq!(
+        Err(yenc::DecodeError::InvalidChecksum),
+        yenc::decode_message(&input)
+    );
+}
+
+#[test]
+fn decode_error_offset() {
+    let data = include_bytes!("../testdata/yenc.org/testfile.txt.yenc");
+    let footer = data.windows(6).position(|w| w == b"=yend ").unwrap();
+    let mut input = data[..footer].to_vec();
+    input.extend_from_slice(b"=yend size=x\r\n");
+
+    let mut decoded = Vec::<u8>::new();
+    match yenc::DecodeOptions::new(temp_dir())
+        .decode_stream_to_writer(input.as_slice(), &mut decoded)
+    {
+        Err(yenc::DecodeError::At { offset, source }) => {
+            assert_eq!(offset, footer);
+            assert!(matches!(
+                *source,
+                yenc::DecodeError::InvalidHeader { position: 11, .. }
+            ));
+        }
+        result => panic!("unexpected result {:?}", result),
+    }
+}
+
+#[test]
+fn decode_cr_line_endings() {
+    let data = include_bytes!("../testdata/yenc.org/testfile.txt.yenc");
+    let expected_decoded = include_bytes!("../testdata/yenc.org/testfile.txt");
+    let mut input = Vec::with_capacity(data.len());
+    for (i, &b) in data.iter().enumerate() {
+        match b {
+            b'\n' if i > 0 && data[i - 1] == b'\r' => {}
+            b'\n' => input.push(b'\r'),
+            _ => input.push(b),
+        }
+    }
+    assert!(!input.contains(&b'\n'));
+
+    let mut decoded = Vec::<u8>::new();
+    let header = yenc::DecodeOptions::new(temp_dir())
+        .strict(true)
+        .decode_stream_to_writer(input.as_slice(), &mut decoded)
+        .unwrap();
+    assert_eq!(decoded.as_slice(), &expected_decoded[..]);
+    assert_eq!(header.name(), Some("testfile.txt"));
+    assert_eq!(header.crc32(), Some(0xded2_9f4f));
+
+    let mut decoder = yenc::Decoder::new(input.as_slice());
+    let mut decoded = Vec::<u8>::new();
+    for chunk in &mut decoder {
+        decoded.extend(chunk.unwrap());
+    }
+    assert_eq!(decoded.as_slice(), &expected_decoded[..]);
+}
+
+#[test]
+fn decode_iterator() {
+    let data = include_bytes!("../testdata/yenc.org/testfile.txt.yenc");
+    let expected_decoded = include_bytes!("../testdata/yenc.org/testfile.txt");
+    let mut decoder = yenc::Decoder::new(&data[..]);
+    assert!(decoder.header().is_none());
+    let mut decoded = Vec::<u8>::new();
+    for chunk in &mut decoder {
+        decoded.extend(chunk.unwrap());
+    }
+    assert_eq!(decoded.as_slice(), &expected_decoded[..]);
+    let header = decoder.header().unwrap();
+    assert_eq!(header.name(), Some("testfile.txt"));
+    assert_eq!(header.crc32(), Some(0xded2_9f4f));
+}
+
+#[test]
+fn decode_reader() {
+    let data = include_bytes!("../testdata/yenc.org/testfile.txt.yenc");
+    let expected_decoded = include_bytes!("../testdata/yenc.org/testfile.txt");
+    let mut reader = yenc::DecodeReader::new(&data[..]);
+    let mut decoded = Vec::<u8>::new();
+    let mut buf = [0u8; 100];
+    loop {
+        let length = reader.read(&mut buf).unwrap();
+        if length == 0 {
+            break;
+        }
+        decoded.extend_from_slice(&buf[..length]);
+    }
+    assert_eq!(decoded.as_slice(), &expected_decoded[..]);
+    assert_eq!(reader.header().unwrap().name(), Some("testfile.txt"));
+    assert!(reader.checksum_ok());
+
+    let mut input = data.to_vec();
+    let pos = input.windows(6).position(|w| w == b"crc32=").unwrap() + 6;
+    input[pos] = if input[pos] == b'0' { b'1' } else { b'0' };
+    let mut reader = yenc::DecodeReader::new(input.as_slice());
+    let err = std::io::copy(&mut reader, &mut std::io::sink()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert!(!reader.checksum_ok());
+}
+
+#[test]
+fn decode_strict_trailing_data() {
+    let data = include_bytes!("../testdata/yenc.org/testfile.txt.yenc");
+    let mut input = data.to_vec();
+    input.extend_from_slice(b"\r\n\r\n");
+    let decode_options = yenc::DecodeOptions::new(temp_dir()).strict(true);
+    let mut decoded = Vec::<u8>::new();
+    assert!(decode_options
+        .decode_stream_to_writer(input.as_slice(), &mut decoded)
+        .is_ok());
+
+    input.extend_from_slice(b"garbage\r\n");
+    let mut decoded = Vec::<u8>::new();
+    match decode_options.decode_stream_to_writer(input.as_slice(), &mut decoded) {
+        Err(yenc::DecodeError::TrailingData { bytes }) => assert_eq!(13, bytes),
+        other => panic!("unexpected result {:?}", other),
+    }
+}
+
+#[test]
+fn decode_strict_missing_footer() {
+    let data = include_bytes!("../testdata/yenc.org/testfile.txt.yenc");
+    let footer_start = data.windows(6).position(|w| w == b"=yend ").unwrap();
+    let input = &data[..footer_start];
+
+    let mut decoded = Vec::<u8>::new();
+    let decode_options = yenc::DecodeOptions::new(temp_dir());
+    assert!(decode_options
+        .decode_stream_to_writer(input, &mut decoded)
+        .is_ok());
+
+    let mut decoded = Vec::<u8>::new();
+    let decode_options = yenc::DecodeOptions::new(temp_dir()).strict(true);
+    assert_eq!(
+        Err(yenc::DecodeError::MissingFooter),
+        decode_options.decode_stream_to_writer(input, &mut decoded)
+    );
+}
+
+#[test]
+fn decode_checksum_policy() {
+    let data = include_bytes!("../testdata/yenc.org/testfile.txt.yenc");
+    let crc_start = data.windows(6).position(|w| w == b"crc32=").unwrap() + 6;
+    let mut input = data.to_vec();
+    input[crc_start..crc_start + 8].copy_from_slice(b"00000000");
+    let output_dir = output_dir("yenc_checksum_policy");
+
+    let decode_options = yenc::DecodeOptions::new(&output_dir).overwrite(true);
+    assert_eq!(
+        Err(yenc::DecodeError::InvalidChecksum),
+        decode_options.decode_stream(input.as_slice())
+    );
+
+    let decode_options = yenc::DecodeOptions::new(&output_dir)
+        .overwrite(true)
+        .checksum_policy(yenc::ChecksumPolicy::Warn);
+    let result = decode_options.decode_stream(data.as_slice()).unwrap();
+    assert!(result.checksum_ok);
+
+    let result = decode_options.decode_stream(input.as_slice()).unwrap();
+    assert!(!result.checksum_ok);
+    assert_eq!(Some(0), result.crc32);
+    assert_eq!(0xded2_9f4f, result.computed_crc32);
+    assert!(result.path.exists());
+
+    std::fs::remove_dir_all(output_dir).unwrap();
+}
+
+#[test]
+fn encode_default_options() {
+    let data = include_bytes!("../testdata/yenc.org/testfile.txt");
+    let expected_encoded = include_bytes!("../testdata/yenc.org/testfile.txt.yenc");
+
+    let mut encoded = Vec::<u8>::new();
+    let encode_options = yenc::EncodeOptions::new();
+    let c = std::io::Cursor::new(&data[..]);
+    encode_options
+        .encode_stream(c, &mut encoded, data.len() as u64, "testfile.txt")
+        .unwrap();
+
+    assert_eq!(encoded.as_slice(), &expected_encoded[..]);
+}
+
+#[test]
+fn encode_decode_line_ending_lf() {
+    let data = include_bytes!("../testdata/yenc.org/testfile.txt");
+    let mut encoded = Vec::<u8>::new();
+    let encode_options = yenc::EncodeOptions::new().line_ending(yenc::LineEnding::Lf);
+    encode_options
+        .encode_stream(
+            std::io::Cursor::new(&data[..]),
+            &mut encoded,
+            data.len() as u64,
+            "testfile.txt",
+        )
+        .unwrap();
+    assert!(!encoded.contains(&b'\r'));
+
+    let mut decoded = Vec::<u8>::new();
+    yenc::DecodeOptions::new(temp_dir())
+        .decode_stream_to_writer(encoded.as_slice(), &mut decoded)
+        .unwrap();
+    assert_eq!(decoded.as_slice(), &data[..]);
+}
+
+#[test]
+fn decode_incomplete_single_part() {
+    let data = include_bytes!("../testdata/yenc.org/testfile.txt.yenc");
+    let footer_start = data.windows(11).position(|w| w == b"=yend size=").unwrap() + 11;
+    let mut input = data.to_vec();
+    input[footer_start..footer_start + 3].copy_from_slice(b"600");
+
+    let mut decoded = Vec::<u8>::new();
+    let decode_options = yenc::DecodeOptions::new(temp_dir());
+    assert_eq!(
+        Err(yenc::DecodeError::IncompleteData {
+            expected_size: 600,
+            actual_size: 584
+        }),
+        decode_options.decode_stream_to_writer(input.as_slice(), &mut decoded)
+    );
+}
+
+#[test]
+fn decode_existing_output_file() {
+    let data = include_bytes!("../testdata/yenc.org/testfile.txt.yenc");
+    let output_dir = output_dir("yenc_existing_output_file");
+
+    let decode_options = yenc::DecodeOptions::new(&output_dir);
+    assert!(decode_options.decode_stream(&data[..]).is_ok());
+    match decode_options.decode_stream(&data[..]) {
+        Err(yenc::DecodeError::IoError(err)) => {
+            assert_eq!(std::io::ErrorKind::AlreadyExists, err.kind())
+        }
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    let decode_options = yenc::DecodeOptions::new(&output_dir).overwrite(true);
+    assert!(decode_options.decode_stream(&data[..]).is_ok());
+
+    std::fs::remove_dir_all(output_dir).unwrap();
+}
+
+#[test]
+fn decode_with_progress() {
+    let data = include_bytes!("../testdata/yenc.org/testfile.txt.yenc");
+    let output_dir = output_dir("yenc_decode_with_progress");
+
+    let mut progress = Vec::new();
+    let decode_options = yenc::DecodeOptions::new(&output_dir);
+    decode_options
+        .decode_stream_with_progress(&data[..], |decoded, total| progress.push((decoded, total)))
+        .unwrap();
+    assert!(progress.len() > 1);
+    assert!(progress.windows(2).all(|w| w[0].0 < w[1].0));
+    assert_eq!(Some(&(584, Some(584))), progress.last());
+
+    std::fs::remove_dir_all(output_dir).unwrap();
+}
+
+#[test]
+fn decode_with_running_crc() {
+    let data = include_bytes!("../testdata/yenc.org/testfile.txt");
+    let encoded = include_bytes!("../testdata/yenc.org/testfile.txt.yenc");
+    let output_dir = output_dir("yenc_decode_with_running_crc");
+
+    let mut decoded = Vec::new();
+    let decode_options = yenc::DecodeOptions::new(&output_dir);
+    decode_options
+        .decode_stream_with(&encoded[..], |chunk, running_crc| {
+            decoded.extend_from_slice(chunk);
+            assert_eq!(yenc::crc32(&decoded), running_crc);
+        })
+        .unwrap();
+    assert_eq!(data.to_vec(), decoded);
+
+    std::fs::remove_dir_all(output_dir).unwrap();
+}
+
+#[test]
+fn decode_ignore_unknown_keywords() {
+    let data = b"yEnc";
+    let encoded = yenc::EncodeOptions::new()
+        .extra_header_field("desc", "test")
+        .encode_bytes(data, "unknown_keywords.bin")
+        .unwrap();
+
+    let output_dir = output_dir("yenc_decode_ignore_unknown_keywords");
+    let decode_options = yenc::DecodeOptions::new(&output_dir).overwrite(true);
+    assert!(matches!(
+        decode_options.decode_stream(encoded.as_slice()),
+        Err(yenc::DecodeError::At { .. })
+    ));
+    let result = decode_options
+        .ignore_unknown_keywords(true)
+        .decode_stream(encoded.as_slice())
+        .unwrap();
+    assert_eq!(data.to_vec(), std::fs::read(result.path).unwrap());
+}
+
+#[test]
+fn decode_resume_from() {
+    let data = include_bytes!("../testdata/yenc.org/testfile.txt");
+    let encoded = include_bytes!("../testdata/yenc.org/testfile.txt.yenc");
+    let output_dir = output_dir("yenc_decode_resume_from");
+    let output_path = output_dir.join("testfile.txt");
+
+    // interrupted after the header line and two data lines
+    let line_ends = encoded
+        .iter()
+        .enumerate()
+        .filter(|&(_, &b)| b == b'\n')
+        .map(|(i, _)| i + 1)
+        .collect::<Vec<usize>>();
+    let stream_offset = line_ends[2];
+    let decoded = yenc::decode_buffer(&encoded[line_ends[0]..stream_offset]).unwrap();
+    let mut partial = decoded.clone();
+    partial.resize(data.len(), 0);
+    std::fs::write(&output_path, &partial).unwrap();
+
+    let result = yenc::DecodeOptions::new(&output_dir)
+        .resume_from(stream_offset as u64, decoded.len() as u64)
+        .decode_stream(&encoded[..])
+        .unwrap();
+    assert!(result.checksum_ok);
+    assert_eq!(data.to_vec(), std::fs::read(&output_path).unwrap());
+
+    let mut running_crcs = Vec::new();
+    yenc::DecodeOptions::new(&output_dir)
+        .resume_from(stream_offset as u64, decoded.len() as u64)
+        .decode_stream_with(&encoded[..], |_, running_crc| {
+            running_crcs.push(running_crc)
+        })
+        .unwrap();
+    assert_eq!(Some(&0xded29f4f), running_crcs.last());
+
+    let err = yenc::DecodeOptions::new(&output_dir)
+        .resume_from(stream_offset as u64 + 1, decoded.len() as u64)
+        .decode_stream(&encoded[..])
+        .unwrap_err();
+    assert!(
+        matches!(err, yenc::DecodeError::IoError(ref err) if err.kind() == std::io::ErrorKind::InvalidInput)
+    );
+}
+
+#[test]
+fn decode_data_line_starting_with_escaped_y() {
+    let body = b"=yendx\r\n=ybegin \r\n";
+    let decoded = yenc::decode_buffer(body).unwrap();
+    assert_eq!(0x0f, decoded[0]);
+    let input = [
+        format!("=ybegin line=128 size={} name=y.bin\r\n", decoded.len()).as_bytes(),
+        body,
+        format!(
+            "=yend size={} crc32={:08x}\r\n",
+            decoded.len(),
+            yenc::crc32(&decoded)
+        )
+        .as_bytes(),
+    ]
+    .concat();
+    let mut output = Vec::new();
+    let header = yenc::DecodeOptions::new(temp_dir())
+        .decode_stream_to_writer(input.as_slice(), &mut output)
+        .unwrap();
+    assert_eq!(decoded, output);
+    assert_eq!(Some(yenc::crc32(&decoded)), header.crc32());
 }